@@ -11,6 +11,32 @@ module alu_unit(
     output logic [31:0] data_o
 );
 
+    function automatic logic [31:0] clz(logic [31:0] x);
+        clz = 32;
+        for (int i = 0; i < 32; i++)
+            if (x[i]) clz = 31 - i;
+    endfunction
+
+    function automatic logic [31:0] ctz(logic [31:0] x);
+        ctz = 32;
+        for (int i = 31; i >= 0; i--)
+            if (x[i]) ctz = i;
+    endfunction
+
+    // Signed division rounding toward zero. Dividing by zero gives 0, and
+    // the one overflowing case, 0x80000000 / -1, wraps to 0x80000000 with
+    // remainder 0.
+    function automatic logic [31:0] sdiv(logic [31:0] a, logic [31:0] b);
+        if (b == 32'b0) sdiv = 32'b0;
+        else if (b == 32'hffffffff) sdiv = -a;
+        else sdiv = $signed(a) / $signed(b);
+    endfunction
+
+    function automatic logic [31:0] smod(logic [31:0] a, logic [31:0] b);
+        if (b == 32'b0 || b == 32'hffffffff) smod = 32'b0;
+        else smod = $signed(a) % $signed(b);
+    endfunction
+
     always @(posedge clk_i) begin
         if (rst_i) begin
             data_o <= 32'b0;
@@ -23,15 +49,24 @@ module alu_unit(
                 ALU_MUL: data_o <= a_data_i*b_data_i;
                 ALU_MOD: data_o <= a_data_i%b_data_i;
                 ALU_EQL: data_o <= a_data_i == b_data_i;
-                ALU_SL: data_o <= a_data_i << b_data_i;
-                ALU_SR: data_o <= a_data_i >> b_data_i;
-                ALU_SRA: data_o <= a_data_i >>> b_data_i;
+                // Shift amounts are taken mod 32, as on most hardware.
+                ALU_SL: data_o <= a_data_i << b_data_i[4:0];
+                ALU_SR: data_o <= a_data_i >> b_data_i[4:0];
+                ALU_SRA: data_o <= a_data_i >>> b_data_i[4:0];
                 ALU_NOT: data_o <= ~a_data_i; // what about not b?
                 ALU_AND: data_o <= a_data_i && b_data_i;
                 ALU_OR: data_o <= a_data_i || b_data_i;
                 ALU_XOR: data_o <= ^ a_data_i; // what about ^ b;?
                 ALU_GT: data_o <= a_data_i > b_data_i;
                 ALU_LT: data_o <= a_data_i < b_data_i;
+                ALU_MULHU: data_o <= 32'(({32'b0, a_data_i} * {32'b0, b_data_i}) >> 32);
+                ALU_ABS: data_o <= a_data_i[31] ? -a_data_i : a_data_i;
+                ALU_NEG: data_o <= -a_data_i;
+                ALU_CLZ: data_o <= clz(a_data_i);
+                ALU_CTZ: data_o <= ctz(a_data_i);
+                ALU_POPCNT: data_o <= 32'($countones(a_data_i));
+                ALU_SDIV: data_o <= sdiv(a_data_i, b_data_i);
+                ALU_SMOD: data_o <= smod(a_data_i, b_data_i);
             endcase
         end
     end
@@ -37,6 +37,7 @@ module cmod_a35t_top (
     tta tta(
         .rst_i(rst_i),
         .clk_i(sysclk_i),
+        .reset_vector_i(32'b0),
         .instr_bus(bootmem_bus),
         .data_bus(data_bus)
     );
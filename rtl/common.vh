@@ -1,23 +1,34 @@
 `ifndef common_vh_
 `define common_vh_
 
-typedef enum bit [3:0] {
-    ALU_NOP = 4'h000,
-    ALU_ADD = 4'h001,
-    ALU_SUB = 4'h002,
-    ALU_MUL = 4'h003,
-    ALU_DIV = 4'h004,
-    ALU_MOD = 4'h005,
-    ALU_EQL = 4'h006,
-    ALU_SL = 4'h007,
-    ALU_SR = 4'h008,
-    ALU_SRA = 4'h009,
-    ALU_NOT = 4'h00a,
-    ALU_AND = 4'h00b,
-    ALU_OR = 4'h00c,
-    ALU_XOR = 4'h00d,
-    ALU_GT = 4'h00e,
-    ALU_LT = 4'h00f
+`define NUM_REGISTERS 32
+`define NUM_ALUS 8
+
+typedef enum bit [4:0] {
+    ALU_NOP = 5'h00,
+    ALU_ADD = 5'h01,
+    ALU_SUB = 5'h02,
+    ALU_MUL = 5'h03,
+    ALU_DIV = 5'h04,
+    ALU_MOD = 5'h05,
+    ALU_EQL = 5'h06,
+    ALU_SL = 5'h07,
+    ALU_SR = 5'h08,
+    ALU_SRA = 5'h09,
+    ALU_NOT = 5'h0a,
+    ALU_AND = 5'h0b,
+    ALU_OR = 5'h0c,
+    ALU_XOR = 5'h0d,
+    ALU_GT = 5'h0e,
+    ALU_LT = 5'h0f,
+    ALU_MULHU = 5'h10, // Upper 32 bits of the unsigned 64-bit product
+    ALU_ABS = 5'h11,   // Two's complement of the left operand, if negative
+    ALU_NEG = 5'h12,   // Two's complement of the left operand
+    ALU_CLZ = 5'h13,   // Leading zero bits of the left operand, 32 for 0
+    ALU_CTZ = 5'h14,   // Trailing zero bits of the left operand, 32 for 0
+    ALU_POPCNT = 5'h15, // Set bits in the left operand
+    ALU_SDIV = 5'h16,   // Signed quotient, rounded toward zero
+    ALU_SMOD = 5'h17    // Signed remainder, with the sign of the left operand
 } ALU_OPERATOR;
 
 typedef enum bit[3:0] {
@@ -34,7 +45,8 @@ typedef enum bit[3:0] {
     UNIT_PC = 10,
     UNIT_ABS_IMMEDIATE = 11,
     UNIT_ABS_OPERAND = 12,
-    UNIT_REGISTER_POINTER = 13  // Value of memory address in register N
+    UNIT_REGISTER_POINTER = 13,  // Value of memory address in register N
+    UNIT_PC_IF = 14  // PC, but only written if ALU N's last result is non-zero
 } Unit;
 
 `endif  // common_vh_
@@ -1,5 +1,4 @@
-`define NUM_REGISTERS 32
-`define NUM_ALUS 8
+`include "common.vh"
 
 module execute(
     input wire clk_i,
@@ -13,6 +12,15 @@ module execute(
     input logic [11:0] dst_immediate_i,
     input logic [31:0] dst_operand_i,
     bus_if.master data_bus,
+    // Set when the last completed move targeted UNIT_PC; the sequencer
+    // fetches the next instruction from pc_load_value_o instead.
+    output logic pc_load_o,
+    output logic [31:0] pc_load_value_o,
+    // The value the current or last completed move carried to its
+    // destination.
+    output wire [31:0] move_value_o,
+    // Every register's contents, register N in bits [N*32 +: 32].
+    output logic [`NUM_REGISTERS*32-1:0] registers_o,
     output logic done_o
 );
     // Registers.
@@ -20,14 +28,20 @@ module execute(
     logic reg_unit_write[`NUM_REGISTERS-1:0];
     logic [31:0] reg_in_data[`NUM_REGISTERS-1:0];
     logic [31:0] reg_out_data[`NUM_REGISTERS-1:0];
+    logic [31:0] reg_values[`NUM_REGISTERS-1:0];
     register_unit register_units[`NUM_REGISTERS-1:0] (
         .rst_i(rst_i),
         .clk_i(clk_i),
         .sel_i(reg_unit_select),
         .wstrb_i(reg_unit_write),
         .data_i(reg_in_data),
-        .data_o(reg_out_data)
+        .data_o(reg_out_data),
+        .value_o(reg_values)
     );
+    always_comb begin
+        for (int i = 0; i < `NUM_REGISTERS; i++)
+            registers_o[i*32 +: 32] = reg_values[i];
+    end
 
     // ALUs.
     logic alu_select[`NUM_ALUS-1:0];
@@ -54,6 +68,7 @@ module execute(
     } ExecState;
     ExecState exec_state;
     logic [31:0] src_value;
+    assign move_value_o = src_value;
     always @(posedge clk_i) begin
         if (rst_i) begin
             reg_unit_select = '{default:1'b0};
@@ -61,6 +76,7 @@ module execute(
 
             alu_select = '{default:1'b0};
             alu_operation = '{default:ALU_NOP};
+            pc_load_o = 1'b0;
             done_o = 1'b0;
         end else if (sel_i) begin
             case (exec_state)
@@ -99,6 +115,10 @@ module execute(
                             src_value = alu_in_data_b[src_immediate_i];
                             exec_state = EXEC_START_DST;
                         end
+                        UNIT_ALU_OPERATOR: begin
+                            src_value = 32'(alu_operation[src_immediate_i]);
+                            exec_state = EXEC_START_DST;
+                        end
                         UNIT_ALU_RESULT: begin
                             alu_select[src_immediate_i] = 1'b1;
                             exec_state = EXEC_SRC_ALU_RETRIEVE;
@@ -112,12 +132,19 @@ module execute(
                             exec_state = EXEC_START_DST;
                         end
                         UNIT_PC: begin
+                            // By now the sequencer has moved past this move and
+                            // its operands, so this is the next instruction's
+                            // address.
                             src_value = pc_i;
+                            exec_state = EXEC_START_DST;
                         end
                         UNIT_NONE: begin
                             src_value = 32'b0;
-                            // Don't waste an extra clock cycle on no-op instructions.
-                            if (dst_unit_i != UNIT_NONE) exec_state = EXEC_START_DST;
+                            // Even a move from nowhere to nowhere goes on to the
+                            // destination, so done_o drops for a cycle first.
+                            // Finishing here would leave it high from the move
+                            // before, and the bubble would never show as retired.
+                            exec_state = EXEC_START_DST;
                         end
                         default: exec_state = EXEC_START_DST;
 
@@ -141,6 +168,11 @@ module execute(
                 // without waiting for the next clock in those cases.
                 // Register to register for example should be one cycle.
                 EXEC_START_DST: begin
+                    // Held until the next move completes, so the sequencer sees it
+                    // whenever it starts the following fetch.
+                    pc_load_o = dst_unit_i == UNIT_PC ||
+                        (dst_unit_i == UNIT_PC_IF && alu_out_data[dst_immediate_i] != 32'b0);
+                    pc_load_value_o = src_value;
                     case (dst_unit_i) inside
                         UNIT_REGISTER: begin
                             reg_unit_select[dst_immediate_i] = 1'b1;
@@ -172,13 +204,13 @@ module execute(
                                 exec_state = EXEC_START_SRC;
                             end
                         end
-                        UNIT_MEMORY_OPERAND, UNIT_MEMORY_IMMEDIATE: begin
+                        UNIT_MEMORY_OPERAND, UNIT_MEMORY_IMMEDIATE, UNIT_REGISTER_POINTER: begin
                             case (dst_unit_i)
                                 UNIT_MEMORY_OPERAND: data_bus.addr = dst_operand_i;
                                 UNIT_MEMORY_IMMEDIATE: data_bus.addr = dst_immediate_i;
                                 UNIT_REGISTER_POINTER: begin
-                                    reg_unit_select[src_immediate_i] = 1'b1;
-                                    data_bus.addr = reg_out_data[src_immediate_i];
+                                    reg_unit_select[dst_immediate_i] = 1'b1;
+                                    data_bus.addr = reg_out_data[dst_immediate_i];
                                 end
                             endcase
 
@@ -4,9 +4,12 @@ module register_unit(
     input wire sel_i,
     input wire wstrb_i,
     input logic [31:0] data_i,
-    output logic [31:0] data_o
+    output logic [31:0] data_o,
+    // The register's contents, for observing the core in tests.
+    output wire [31:0] value_o
 );
     reg [31:0] r;
+    assign value_o = r;
 
     always @(posedge clk_i) begin
         if (rst_i) r <= 32'b0;
@@ -1,6 +1,7 @@
 module sequencer(
     input wire clk_i,
     input wire rst_i,
+    input wire [31:0] reset_vector_i,
     bus_if.master instr_bus,
     output logic [31:0] pc_o,
     output logic [31:0] op_o,
@@ -9,6 +10,8 @@ module sequencer(
 
     input logic need_src_operand_i,
     input logic need_dst_operand_i,
+    input logic pc_load_i,
+    input logic [31:0] pc_load_value_i,
     input logic sel_i,
     output wire decoder_enable_o,
 
@@ -30,13 +33,14 @@ module sequencer(
 
     always @(posedge clk_i) begin
         if (rst_i) begin
-            pc_o = 32'b0;
+            pc_o = reset_vector_i;
             op_o = 32'b0;
             sequencer_state = SEQ_START;
             instr_bus.valid = 1'b0;
         end else if (sel_i) begin
             case (sequencer_state)
                 SEQ_START: begin
+                    if (pc_load_i) pc_o = pc_load_value_i;
                     instr_bus.valid = 1'b1;
                     instr_bus.instr = 1'b1;
                     instr_bus.addr = pc_o;
@@ -2,8 +2,16 @@
 module tta(
     input wire rst_i,
     input wire clk_i,
+    // Address the first instruction is fetched from after reset.
+    input wire [31:0] reset_vector_i,
 
     output wire instr_done_o,
+    // The sequencer's program counter, for observing the core in tests.
+    output wire [31:0] pc_o,
+    // The value carried by the last completed move, likewise.
+    output wire [31:0] move_value_o,
+    // Every register's contents, register N in bits [N*32 +: 32], likewise.
+    output wire [`NUM_REGISTERS*32-1:0] registers_o,
 
     bus_if.master instr_bus,
     bus_if.master data_bus
@@ -14,8 +22,11 @@ module tta(
     logic [31:0] dst_operand;
     logic [31:0] op;
     logic done_exec;
+    logic pc_load;
+    logic [31:0] pc_load_value;
 
     assign instr_done_o = done_exec;
+    assign pc_o = pc;
 
     logic need_src_operand;
     logic need_dst_operand;
@@ -25,6 +36,7 @@ module tta(
     sequencer sequencer(
         .clk_i(clk_i),
         .rst_i(rst_i),
+        .reset_vector_i(reset_vector_i),
         .instr_bus(instr_bus),
         .pc_o(pc),
         .op_o(op),
@@ -34,6 +46,8 @@ module tta(
         .dst_operand_o(dst_operand),
         .decoder_enable_o(decoder_enable),
         .need_dst_operand_i(need_dst_operand),
+        .pc_load_i(pc_load),
+        .pc_load_value_i(pc_load_value),
         .done_o(sequencer_done)
     );
     Unit src_unit;
@@ -66,6 +80,10 @@ module tta(
         .dst_unit_i(dst_unit),
         .dst_immediate_i(di),
         .dst_operand_i(dst_operand),
+        .pc_load_o(pc_load),
+        .pc_load_value_o(pc_load_value),
+        .move_value_o(move_value_o),
+        .registers_o(registers_o),
         .done_o(done_exec)
     );
 
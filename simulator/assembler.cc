@@ -1,5 +1,12 @@
 #include "assembler.h"
 
+#include <algorithm>
+#include <fstream>
+#include <iomanip>
+#include <iterator>
+#include <map>
+#include <sstream>
+
 namespace {
 
 bool NeedsOperand(Unit u) {
@@ -15,6 +22,8 @@ bool NeedsOperand(Unit u) {
     case Unit::UNIT_MEMORY_IMMEDIATE:
     case Unit::UNIT_PC:
     case Unit::UNIT_ABS_IMMEDIATE:
+    case Unit::UNIT_REGISTER_POINTER:
+    case Unit::UNIT_PC_IF:
       return false;
     case Unit::UNIT_MEMORY_OPERAND:
     case Unit::UNIT_ABS_OPERAND:
@@ -22,19 +31,346 @@ bool NeedsOperand(Unit u) {
   }
   return false;
 }
+
+// Cycle costs, see CyclesFor(). The opcode fetch covers SEQ_START,
+// SEQ_READ_OPCODE (ready comes a cycle after valid) and SEQ_DECODE.
+constexpr int kFetchCycles = 3;
+constexpr int kOperandCycles = 2;
+// EXEC_START_SRC, then waiting in EXEC_SRC_MEM_RETRIEVE for ready.
+constexpr int kMemorySourceCycles = 3;
+// EXEC_START_SRC then EXEC_SRC_ALU_RETRIEVE.
+constexpr int kAluResultSourceCycles = 2;
+constexpr int kSourceCycles = 1;
+// EXEC_START_DST. Memory writes are posted, so they cost no more.
+constexpr int kDestCycles = 1;
 }  // namespace
 
+std::ostream& operator<<(std::ostream& os, ALUOp op) {
+  switch (op) {
+    case ALUOp::ALU_NOP:
+      return os << "ALU_NOP";
+    case ALUOp::ALU_ADD:
+      return os << "ALU_ADD";
+    case ALUOp::ALU_SUB:
+      return os << "ALU_SUB";
+    case ALUOp::ALU_MUL:
+      return os << "ALU_MUL";
+    case ALUOp::ALU_DIV:
+      return os << "ALU_DIV";
+    case ALUOp::ALU_MOD:
+      return os << "ALU_MOD";
+    case ALUOp::ALU_EQL:
+      return os << "ALU_EQL";
+    case ALUOp::ALU_SL:
+      return os << "ALU_SL";
+    case ALUOp::ALU_SR:
+      return os << "ALU_SR";
+    case ALUOp::ALU_SRA:
+      return os << "ALU_SRA";
+    case ALUOp::ALU_NOT:
+      return os << "ALU_NOT";
+    case ALUOp::ALU_AND:
+      return os << "ALU_AND";
+    case ALUOp::ALU_OR:
+      return os << "ALU_OR";
+    case ALUOp::ALU_XOR:
+      return os << "ALU_XOR";
+    case ALUOp::ALU_GT:
+      return os << "ALU_GT";
+    case ALUOp::ALU_LT:
+      return os << "ALU_LT";
+    case ALUOp::ALU_MULHU:
+      return os << "ALU_MULHU";
+    case ALUOp::ALU_ABS:
+      return os << "ALU_ABS";
+    case ALUOp::ALU_NEG:
+      return os << "ALU_NEG";
+    case ALUOp::ALU_CLZ:
+      return os << "ALU_CLZ";
+    case ALUOp::ALU_CTZ:
+      return os << "ALU_CTZ";
+    case ALUOp::ALU_POPCNT:
+      return os << "ALU_POPCNT";
+    case ALUOp::ALU_SDIV:
+      return os << "ALU_SDIV";
+    case ALUOp::ALU_SMOD:
+      return os << "ALU_SMOD";
+  }
+  return os << "ALU_" << (int)op;
+}
+
+bool IsCommutative(ALUOp op) {
+  switch (op) {
+    case ALUOp::ALU_ADD:
+    case ALUOp::ALU_MUL:
+    case ALUOp::ALU_MULHU:
+    case ALUOp::ALU_EQL:
+    case ALUOp::ALU_AND:
+    case ALUOp::ALU_OR:
+      return true;
+    default:
+      return false;
+  }
+}
+
+bool IsUnary(ALUOp op) {
+  switch (op) {
+    case ALUOp::ALU_NOP:
+    case ALUOp::ALU_NOT:
+    case ALUOp::ALU_XOR:
+    case ALUOp::ALU_ABS:
+    case ALUOp::ALU_NEG:
+    case ALUOp::ALU_CLZ:
+    case ALUOp::ALU_CTZ:
+    case ALUOp::ALU_POPCNT:
+      return true;
+    default:
+      return false;
+  }
+}
+
+std::optional<uint32_t> IdentityElement(ALUOp op) {
+  switch (op) {
+    case ALUOp::ALU_ADD:
+    case ALUOp::ALU_SUB:
+    case ALUOp::ALU_SL:
+    case ALUOp::ALU_SR:
+    case ALUOp::ALU_SRA:
+      return 0;
+    case ALUOp::ALU_MUL:
+    case ALUOp::ALU_DIV:
+    case ALUOp::ALU_SDIV:
+      return 1;
+    default:
+      return std::nullopt;
+  }
+}
+
+std::ostream& operator<<(std::ostream& os, Unit u) {
+  switch (u) {
+    case Unit::UNIT_NONE:
+      return os << "UNIT_NONE";
+    case Unit::UNIT_STACK_PUSH_POP:
+      return os << "UNIT_STACK_PUSH_POP";
+    case Unit::UNIT_STACK_INDEX:
+      return os << "UNIT_STACK_INDEX";
+    case Unit::UNIT_REGISTER:
+      return os << "UNIT_REGISTER";
+    case Unit::UNIT_ALU_LEFT:
+      return os << "UNIT_ALU_LEFT";
+    case Unit::UNIT_ALU_RIGHT:
+      return os << "UNIT_ALU_RIGHT";
+    case Unit::UNIT_ALU_OPERATOR:
+      return os << "UNIT_ALU_OPERATOR";
+    case Unit::UNIT_ALU_RESULT:
+      return os << "UNIT_ALU_RESULT";
+    case Unit::UNIT_MEMORY_IMMEDIATE:
+      return os << "UNIT_MEMORY_IMMEDIATE";
+    case Unit::UNIT_MEMORY_OPERAND:
+      return os << "UNIT_MEMORY_OPERAND";
+    case Unit::UNIT_PC:
+      return os << "UNIT_PC";
+    case Unit::UNIT_ABS_IMMEDIATE:
+      return os << "UNIT_ABS_IMMEDIATE";
+    case Unit::UNIT_ABS_OPERAND:
+      return os << "UNIT_ABS_OPERAND";
+    case Unit::UNIT_REGISTER_POINTER:
+      return os << "UNIT_REGISTER_POINTER";
+    case Unit::UNIT_PC_IF:
+      return os << "UNIT_PC_IF";
+  }
+  return os << "UNIT_" << (int)u;
+}
+
+bool IsValidSource(Unit u) {
+  switch (u) {
+    case Unit::UNIT_NONE:
+    case Unit::UNIT_REGISTER:
+    case Unit::UNIT_ALU_LEFT:
+    case Unit::UNIT_ALU_RIGHT:
+    case Unit::UNIT_ALU_OPERATOR:
+    case Unit::UNIT_ALU_RESULT:
+    case Unit::UNIT_MEMORY_IMMEDIATE:
+    case Unit::UNIT_MEMORY_OPERAND:
+    case Unit::UNIT_PC:
+    case Unit::UNIT_ABS_IMMEDIATE:
+    case Unit::UNIT_ABS_OPERAND:
+    case Unit::UNIT_REGISTER_POINTER:
+      return true;
+    default:
+      return false;
+  }
+}
+
+bool IsValidDest(Unit u) {
+  switch (u) {
+    case Unit::UNIT_NONE:
+    case Unit::UNIT_REGISTER:
+    case Unit::UNIT_ALU_LEFT:
+    case Unit::UNIT_ALU_RIGHT:
+    case Unit::UNIT_ALU_OPERATOR:
+    case Unit::UNIT_MEMORY_IMMEDIATE:
+    case Unit::UNIT_MEMORY_OPERAND:
+    case Unit::UNIT_PC:
+    case Unit::UNIT_PC_IF:
+    case Unit::UNIT_REGISTER_POINTER:
+      return true;
+    default:
+      return false;
+  }
+}
+
+std::ostream& operator<<(std::ostream& os, const AssembleError& e) {
+  const char* role = e.role == Role::SOURCE ? "source" : "destination";
+  switch (e.kind) {
+    case AssembleError::Kind::ILLEGAL_DIRECTION:
+      return os << e.unit << " can't be used as a " << role;
+    case AssembleError::Kind::MISSING_OPERAND:
+      return os << e.unit << " as " << role << " needs an operand";
+  }
+  return os;
+}
+
+std::ostream& operator<<(std::ostream& os, const DecodeError& e) {
+  const char* role = e.role == Role::SOURCE ? "source" : "destination";
+  switch (e.kind) {
+    case DecodeError::Kind::TRUNCATED_OPERAND:
+      return os << e.unit << " as " << role << " is missing its operand";
+  }
+  return os;
+}
+
+Instr Instr::Decode(const std::vector<uint32_t>& words,
+                    size_t pos,
+                    size_t* len) {
+  Instr instr;
+  DecodeError error;
+  CHECK(TryDecode(words, pos, &instr, len, &error)) << error;
+  return instr;
+}
+
+bool Instr::TryDecode(const std::vector<uint32_t>& words,
+                      size_t pos,
+                      Instr* instr,
+                      size_t* len,
+                      DecodeError* error) {
+  CHECK_LT(pos, words.size());
+  Instr decoded;
+  uint32_t op = words[pos];
+  decoded.op_.src_unit = (op >> kSrcUnitShift) & kSrcUnitMask;
+  decoded.op_.si = (op >> kSiShift) & kSiMask;
+  decoded.op_.dst_unit = (op >> kDstUnitShift) & kDstUnitMask;
+  decoded.op_.di = (op >> kDiShift) & kDiMask;
+  size_t decoded_len = 1;
+  if (decoded.UsesSoperand()) {
+    if (pos + decoded_len >= words.size()) {
+      *error = {DecodeError::Kind::TRUNCATED_OPERAND, decoded.src_unit(),
+                Role::SOURCE};
+      return false;
+    }
+    decoded.soperand_ = words[pos + decoded_len++];
+  }
+  if (decoded.UsesDoperand()) {
+    if (pos + decoded_len >= words.size()) {
+      *error = {DecodeError::Kind::TRUNCATED_OPERAND, decoded.dst_unit(),
+                Role::DESTINATION};
+      return false;
+    }
+    decoded.doperand_ = words[pos + decoded_len++];
+  }
+  *instr = decoded;
+  *len = decoded_len;
+  return true;
+}
+
+int CyclesFor(const Instr& instr) {
+  int cycles = kFetchCycles +
+               kOperandCycles * (instr.UsesSoperand() + instr.UsesDoperand());
+  switch (instr.src_unit()) {
+    case Unit::UNIT_MEMORY_IMMEDIATE:
+    case Unit::UNIT_MEMORY_OPERAND:
+    case Unit::UNIT_REGISTER_POINTER:
+      cycles += kMemorySourceCycles;
+      break;
+    case Unit::UNIT_ALU_RESULT:
+      cycles += kAluResultSourceCycles;
+      break;
+    default:
+      cycles += kSourceCycles;
+      break;
+  }
+  return cycles + kDestCycles;
+}
+
+bool operator==(const Instr& a, const Instr& b) {
+  return a.src_unit() == b.src_unit() && a.si() == b.si() &&
+         a.dst_unit() == b.dst_unit() && a.di() == b.di() &&
+         a.soperand() == b.soperand() && a.doperand() == b.doperand();
+}
+
+bool operator!=(const Instr& a, const Instr& b) {
+  return !(a == b);
+}
+
+size_t std::hash<Instr>::operator()(const Instr& instr) const {
+  size_t h = 0;
+  auto mix = [&h](size_t v) { h = h * 31 + v; };
+  mix((size_t)instr.src_unit());
+  mix(instr.si());
+  mix((size_t)instr.dst_unit());
+  mix(instr.di());
+  mix(std::hash<std::optional<uint32_t>>()(instr.soperand()));
+  mix(std::hash<std::optional<uint32_t>>()(instr.doperand()));
+  return h;
+}
+
 std::vector<uint32_t> Instr::assemble() const {
-  CHECK_EQ(UsesSoperand(), soperand_.has_value());
-  CHECK_EQ(UsesDoperand(), doperand_.has_value());
+  std::vector<uint32_t> prg;
+  AssembleInto(&prg);
+  return prg;
+}
+
+void Instr::AssembleInto(std::vector<uint32_t>* buf) const {
+  AssembleError error;
+  CHECK(TryAssembleInto(buf, &error)) << error;
+}
+
+bool Instr::TryAssemble(std::vector<uint32_t>* prg,
+                        AssembleError* error) const {
+  prg->clear();
+  return TryAssembleInto(prg, error);
+}
+
+bool Instr::TryAssembleInto(std::vector<uint32_t>* prg,
+                            AssembleError* error) const {
+  Unit src = (Unit)op_.src_unit;
+  Unit dst = (Unit)op_.dst_unit;
+  if (!IsValidSource(src)) {
+    *error = {AssembleError::Kind::ILLEGAL_DIRECTION, src, Role::SOURCE};
+    return false;
+  }
+  if (!IsValidDest(dst)) {
+    *error = {AssembleError::Kind::ILLEGAL_DIRECTION, dst, Role::DESTINATION};
+    return false;
+  }
+  if (UsesSoperand() && !soperand_) {
+    *error = {AssembleError::Kind::MISSING_OPERAND, src, Role::SOURCE};
+    return false;
+  }
+  if (UsesDoperand() && !doperand_) {
+    *error = {AssembleError::Kind::MISSING_OPERAND, dst, Role::DESTINATION};
+    return false;
+  }
 
-  std::vector<uint32_t> prg{*reinterpret_cast<const uint32_t*>(&op_)};
+  prg->push_back((op_.src_unit & kSrcUnitMask) << kSrcUnitShift |
+                 (op_.si & kSiMask) << kSiShift |
+                 (op_.dst_unit & kDstUnitMask) << kDstUnitShift |
+                 (op_.di & kDiMask) << kDiShift);
   if (UsesSoperand())
-    prg.emplace_back(soperand_.value());
+    prg->emplace_back(soperand_.value());
   if (UsesDoperand())
-    prg.emplace_back(doperand_.value());
-
-  return std::move(prg);
+    prg->emplace_back(doperand_.value());
+  return true;
 }
 
 bool Instr::UsesSoperand() const {
@@ -58,17 +394,30 @@ Instr& Instr::Dst(Unit u) {
 }
 
 Instr& Instr::Si(const short i) {
-  DCHECK(i < 1U << 12U);
-  op_.si = i;
+  CHECK(TrySi(i)) << "Source immediate " << i << " doesn't fit in 12 bits";
   return *this;
 }
 
 Instr& Instr::Di(const short i) {
-  DCHECK(i < 1U << 12U);
-  op_.di = i;
+  CHECK(TryDi(i)) << "Destination immediate " << i
+                  << " doesn't fit in 12 bits";
   return *this;
 }
 
+bool Instr::TrySi(int i) {
+  if (i < 0 || i > (int)kSiMask)
+    return false;
+  op_.si = i;
+  return true;
+}
+
+bool Instr::TryDi(int i) {
+  if (i < 0 || i > (int)kDiMask)
+    return false;
+  op_.di = i;
+  return true;
+}
+
 Instr& Instr::Soperand(uint32_t o) {
   CHECK(UsesSoperand());
   soperand_ = o;
@@ -79,3 +428,564 @@ Instr& Instr::Doperand(uint32_t o) {
   doperand_ = o;
   return *this;
 }
+
+Instr& Instr::Comment(const std::string& text) {
+  comment_ = text;
+  return *this;
+}
+
+namespace {
+
+// One side of a move, in disassembly notation.
+void PrintUnit(std::ostream& os,
+               Unit u,
+               unsigned short immediate,
+               const std::optional<uint32_t>& operand) {
+  os << std::hex << std::setfill('0');
+  switch (u) {
+    case Unit::UNIT_NONE:
+      os << "_";
+      break;
+    case Unit::UNIT_STACK_PUSH_POP:
+      os << "STACK" << immediate;
+      break;
+    case Unit::UNIT_STACK_INDEX:
+      os << "S" << std::setw(3) << immediate;
+      break;
+    case Unit::UNIT_REGISTER:
+      os << "R" << std::setw(2) << immediate;
+      break;
+    case Unit::UNIT_ALU_LEFT:
+      os << "ALU" << immediate << ":LEFT";
+      break;
+    case Unit::UNIT_ALU_RIGHT:
+      os << "ALU" << immediate << ":RIGHT";
+      break;
+    case Unit::UNIT_ALU_OPERATOR:
+      os << "ALU" << immediate << ":OPERATOR";
+      break;
+    case Unit::UNIT_ALU_RESULT:
+      os << "ALU" << immediate << ":RESULT";
+      break;
+    case Unit::UNIT_MEMORY_IMMEDIATE:
+      os << "*(" << std::setw(3) << immediate << ")";
+      break;
+    case Unit::UNIT_MEMORY_OPERAND:
+      os << "*(" << std::setw(8) << operand.value_or(0) << ")";
+      break;
+    case Unit::UNIT_PC:
+      os << "PC";
+      break;
+    case Unit::UNIT_ABS_IMMEDIATE:
+      os << "#" << std::setw(3) << immediate;
+      break;
+    case Unit::UNIT_ABS_OPERAND:
+      os << "#" << std::setw(8) << operand.value_or(0);
+      break;
+    case Unit::UNIT_REGISTER_POINTER:
+      os << "*R" << std::setw(2) << immediate;
+      break;
+    case Unit::UNIT_PC_IF:
+      os << "PC[ALU" << immediate << "]";
+      break;
+    default:
+      os << "?" << (int)u << ":" << immediate;
+      break;
+  }
+  os << std::dec << std::setfill(' ');
+}
+
+}  // namespace
+
+std::ostream& operator<<(std::ostream& os, const Instr& instr) {
+  Unit src = instr.src_unit();
+  Unit dst = instr.dst_unit();
+  if (src == Unit::UNIT_NONE && dst == Unit::UNIT_NONE) {
+    os << "NOP";
+  } else {
+    PrintUnit(os, dst, instr.di(), instr.doperand());
+    os << " := ";
+    PrintUnit(os, src, instr.si(), instr.soperand());
+  }
+  if (!instr.comment().empty())
+    os << " ; " << instr.comment();
+  return os;
+}
+
+namespace {
+
+// One side of a move, as parsed from assembly text.
+struct ParsedUnit {
+  Unit unit;
+  unsigned short immediate = 0;
+  std::optional<uint32_t> operand;
+};
+
+std::string Trim(const std::string& s) {
+  size_t start = s.find_first_not_of(" \t\r");
+  if (start == std::string::npos)
+    return "";
+  size_t end = s.find_last_not_of(" \t\r");
+  return s.substr(start, end - start + 1);
+}
+
+bool ParseHex(const std::string& s, uint32_t* value) {
+  if (s.empty() || s.size() > 8 ||
+      s.find_first_not_of("0123456789abcdefABCDEF") != std::string::npos)
+    return false;
+  *value = std::stoul(s, nullptr, 16);
+  return true;
+}
+
+bool ParseImmediate(const std::string& s, unsigned short* immediate) {
+  uint32_t value;
+  if (!ParseHex(s, &value) || value >= 1U << 12U)
+    return false;
+  *immediate = value;
+  return true;
+}
+
+// Parses "<prefix><hex><suffix>" into an immediate.
+bool ParseIndexed(const std::string& token,
+                  const std::string& prefix,
+                  const std::string& suffix,
+                  unsigned short* immediate) {
+  if (token.size() <= prefix.size() + suffix.size() ||
+      token.compare(0, prefix.size(), prefix) != 0 ||
+      token.compare(token.size() - suffix.size(), suffix.size(), suffix) != 0)
+    return false;
+  return ParseImmediate(
+      token.substr(prefix.size(),
+                   token.size() - prefix.size() - suffix.size()),
+      immediate);
+}
+
+// Parses an immediate-or-operand value as used by "*(...)" and "#...".
+bool ParseImmediateOrOperand(const std::string& digits,
+                             Unit immediate_unit,
+                             Unit operand_unit,
+                             ParsedUnit* parsed) {
+  if (digits.size() <= 3) {
+    parsed->unit = immediate_unit;
+    return ParseImmediate(digits, &parsed->immediate);
+  }
+  uint32_t operand;
+  if (!ParseHex(digits, &operand))
+    return false;
+  parsed->unit = operand_unit;
+  parsed->operand = operand;
+  return true;
+}
+
+bool ParseUnit(const std::string& token, ParsedUnit* parsed) {
+  if (token == "_") {
+    parsed->unit = Unit::UNIT_NONE;
+    return true;
+  }
+  if (token == "PC") {
+    parsed->unit = Unit::UNIT_PC;
+    return true;
+  }
+  if (ParseIndexed(token, "PC[ALU", "]", &parsed->immediate)) {
+    parsed->unit = Unit::UNIT_PC_IF;
+    return true;
+  }
+  static const std::pair<const char*, Unit> kAluPorts[] = {
+      {":LEFT", Unit::UNIT_ALU_LEFT},
+      {":RIGHT", Unit::UNIT_ALU_RIGHT},
+      {":OPERATOR", Unit::UNIT_ALU_OPERATOR},
+      {":RESULT", Unit::UNIT_ALU_RESULT}};
+  for (const auto& [suffix, unit] : kAluPorts) {
+    if (ParseIndexed(token, "ALU", suffix, &parsed->immediate)) {
+      parsed->unit = unit;
+      return true;
+    }
+  }
+  if (ParseIndexed(token, "STACK", "", &parsed->immediate)) {
+    parsed->unit = Unit::UNIT_STACK_PUSH_POP;
+    return true;
+  }
+  if (ParseIndexed(token, "S", "", &parsed->immediate)) {
+    parsed->unit = Unit::UNIT_STACK_INDEX;
+    return true;
+  }
+  if (ParseIndexed(token, "R", "", &parsed->immediate)) {
+    parsed->unit = Unit::UNIT_REGISTER;
+    return true;
+  }
+  if (ParseIndexed(token, "*R", "", &parsed->immediate)) {
+    parsed->unit = Unit::UNIT_REGISTER_POINTER;
+    return true;
+  }
+  if (token.size() > 3 && token.compare(0, 2, "*(") == 0 &&
+      token.back() == ')') {
+    return ParseImmediateOrOperand(token.substr(2, token.size() - 3),
+                                   Unit::UNIT_MEMORY_IMMEDIATE,
+                                   Unit::UNIT_MEMORY_OPERAND, parsed);
+  }
+  if (token.size() > 1 && token[0] == '#') {
+    return ParseImmediateOrOperand(token.substr(1), Unit::UNIT_ABS_IMMEDIATE,
+                                   Unit::UNIT_ABS_OPERAND, parsed);
+  }
+  return false;
+}
+
+}  // namespace
+
+bool ParseAsm(const std::string& text, Program* program, ParseError* error) {
+  std::istringstream in(text);
+  std::string line;
+  int line_no = 0;
+  while (std::getline(in, line)) {
+    line_no++;
+    auto fail = [&](const std::string& message) {
+      *error = {line_no, line, message};
+      return false;
+    };
+
+    size_t semicolon = line.find(';');
+    std::string code = Trim(line.substr(0, semicolon));
+    if (code.empty())
+      continue;
+    // A comment after a move stays attached to it.
+    std::string comment =
+        semicolon == std::string::npos ? "" : Trim(line.substr(semicolon + 1));
+    if (code == "NOP") {
+      program->Add(Instr::Nop().Comment(comment));
+      continue;
+    }
+
+    size_t assign = code.find(":=");
+    if (assign == std::string::npos)
+      return fail("expected \"dst := src\"");
+    std::string dst_token = Trim(code.substr(0, assign));
+    std::string src_token = Trim(code.substr(assign + 2));
+    ParsedUnit src, dst;
+    if (!ParseUnit(dst_token, &dst))
+      return fail("bad destination \"" + dst_token + "\"");
+    if (!ParseUnit(src_token, &src))
+      return fail("bad source \"" + src_token + "\"");
+
+    Instr instr;
+    instr.Src(src.unit).Si(src.immediate).Dst(dst.unit).Di(dst.immediate);
+    if (src.operand)
+      instr.Soperand(*src.operand);
+    if (dst.operand)
+      instr.Doperand(*dst.operand);
+    instr.Comment(comment);
+
+    std::vector<uint32_t> words;
+    AssembleError assemble_error;
+    if (!instr.TryAssemble(&words, &assemble_error)) {
+      std::ostringstream message;
+      message << assemble_error;
+      return fail(message.str());
+    }
+    program->Add(instr);
+  }
+  return true;
+}
+
+std::optional<std::vector<uint32_t>> ReadProgramBinary(const std::string& path,
+                                                       Endianness order) {
+  std::ifstream in(path, std::ios::binary);
+  if (!in)
+    return std::nullopt;
+  std::vector<uint8_t> bytes((std::istreambuf_iterator<char>(in)),
+                             std::istreambuf_iterator<char>());
+  if (bytes.size() % 4 != 0)
+    return std::nullopt;
+  std::vector<uint32_t> words;
+  for (size_t i = 0; i < bytes.size(); i += 4) {
+    uint32_t word = 0;
+    for (int b = 0; b < 4; b++) {
+      int shift = order == Endianness::LITTLE ? 8 * b : 8 * (3 - b);
+      word |= (uint32_t)bytes[i + b] << shift;
+    }
+    words.push_back(word);
+  }
+  return words;
+}
+
+bool WriteProgramBinary(const std::string& path,
+                        const std::vector<uint32_t>& words,
+                        Endianness order) {
+  std::ofstream out(path, std::ios::binary);
+  for (uint32_t word : words) {
+    char bytes[4];
+    for (int b = 0; b < 4; b++) {
+      int shift = order == Endianness::LITTLE ? 8 * b : 8 * (3 - b);
+      bytes[b] = (char)(word >> shift);
+    }
+    out.write(bytes, sizeof(bytes));
+  }
+  return (bool)out;
+}
+
+Program& Program::Add(const Instr& instr) {
+  instrs_.push_back(instr);
+  return *this;
+}
+
+Program& Program::Mov(Unit src, short si, Unit dst, short di) {
+  return Add(Instr().Src(src).Si(si).Dst(dst).Di(di));
+}
+
+Program& Program::Alu(short left, short right, ALUOp op, short idx) {
+  return Mov(Unit::UNIT_REGISTER, left, Unit::UNIT_ALU_LEFT, idx)
+      .Mov(Unit::UNIT_REGISTER, right, Unit::UNIT_ALU_RIGHT, idx)
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, (short)op, Unit::UNIT_ALU_OPERATOR, idx);
+}
+
+Program& Program::LoadConstant(uint32_t value, short reg) {
+  if (value <= kSiMask)
+    return Mov(Unit::UNIT_ABS_IMMEDIATE, (short)value, Unit::UNIT_REGISTER, reg);
+  return Add(Instr()
+                 .Src(Unit::UNIT_ABS_OPERAND)
+                 .Soperand(value)
+                 .Dst(Unit::UNIT_REGISTER)
+                 .Di(reg));
+}
+
+std::vector<uint32_t> Program::assemble() const {
+  std::vector<uint32_t> prg;
+  prg.reserve(LenWords());
+  for (const auto& instr : instrs_)
+    instr.AssembleInto(&prg);
+  return prg;
+}
+
+std::vector<uint32_t> Program::AssembleWithMap(
+    std::vector<size_t>* offsets) const {
+  std::vector<uint32_t> prg;
+  prg.reserve(LenWords());
+  offsets->clear();
+  offsets->reserve(instrs_.size());
+  for (const auto& instr : instrs_) {
+    offsets->push_back(prg.size());
+    instr.AssembleInto(&prg);
+  }
+  return prg;
+}
+
+std::optional<size_t> InstrIndexAt(const Program& program,
+                                   const std::vector<size_t>& offsets,
+                                   uint32_t word) {
+  if (offsets.empty() || word >= program.LenWords())
+    return std::nullopt;
+  auto next = std::upper_bound(offsets.begin(), offsets.end(), word);
+  return next - offsets.begin() - 1;
+}
+
+size_t Program::LenWords() const {
+  size_t words = 0;
+  for (const auto& instr : instrs_)
+    words += instr.LenWords();
+  return words;
+}
+
+int Program::TotalCycles() const {
+  int cycles = 0;
+  for (const auto& instr : instrs_)
+    cycles += CyclesFor(instr);
+  return cycles;
+}
+
+ProgramSoA::ProgramSoA(const Program& program) {
+  for (const Instr& instr : program) {
+    src_units.push_back(instr.src_unit());
+    sis.push_back(instr.si());
+    dst_units.push_back(instr.dst_unit());
+    dis.push_back(instr.di());
+    soperands.push_back(instr.soperand());
+    doperands.push_back(instr.doperand());
+  }
+}
+
+Program ProgramSoA::ToProgram() const {
+  Program program;
+  for (size_t i = 0; i < size(); i++) {
+    Instr instr = Instr()
+                      .Src(src_units[i])
+                      .Si(sis[i])
+                      .Dst(dst_units[i])
+                      .Di(dis[i]);
+    if (soperands[i])
+      instr.Soperand(*soperands[i]);
+    if (doperands[i])
+      instr.Doperand(*doperands[i]);
+    program.Add(instr);
+  }
+  return program;
+}
+
+Instr Instr::LoadViaPointer(short reg, Unit dst, short di) {
+  return Instr().Src(Unit::UNIT_REGISTER_POINTER).Si(reg).Dst(dst).Di(di);
+}
+
+Instr Instr::StoreViaPointer(Unit src, short si, short reg) {
+  return Instr().Src(src).Si(si).Dst(Unit::UNIT_REGISTER_POINTER).Di(reg);
+}
+
+Instr Instr::ReadAluOp(short alu_index, Unit dst, short di) {
+  return Instr().Src(Unit::UNIT_ALU_OPERATOR).Si(alu_index).Dst(dst).Di(di);
+}
+
+Instr Instr::JumpReg(short reg) {
+  return Instr().Src(Unit::UNIT_REGISTER).Si(reg).Dst(Unit::UNIT_PC);
+}
+
+Instr Instr::ReadPc(Unit dst, short di) {
+  return Instr().Src(Unit::UNIT_PC).Dst(dst).Di(di);
+}
+
+Instr Instr::Nop() {
+  return Instr().Src(Unit::UNIT_NONE).Dst(Unit::UNIT_NONE);
+}
+
+LabeledInstr Instr::Label(const std::string& name) {
+  LabeledInstr l;
+  l.label_ = name;
+  return l;
+}
+
+LabeledInstr Instr::Jump(const std::string& label) {
+  return LabeledInstr::WithTarget(
+      Instr().Src(Unit::UNIT_ABS_OPERAND).Dst(Unit::UNIT_PC), label);
+}
+
+LabeledInstr LabeledInstr::WithTarget(const Instr& instr,
+                                      const std::string& label) {
+  CHECK(instr.UsesSoperand());
+  LabeledInstr l(instr);
+  l.target_ = label;
+  return l;
+}
+
+std::vector<uint32_t> AssembleLabeled(const LabeledProgram& program,
+                                      uint32_t base) {
+  std::map<std::string, uint32_t> labels;
+  uint32_t pos = base;
+  for (const auto& l : program) {
+    if (l.label()) {
+      CHECK(labels.emplace(*l.label(), pos).second)
+          << "Duplicate label: " << *l.label();
+      continue;
+    }
+    pos += l.instr().LenWords();
+  }
+
+  std::vector<uint32_t> prg;
+  prg.reserve(pos - base);
+  for (const auto& l : program) {
+    if (l.label())
+      continue;
+    Instr instr = l.instr();
+    if (l.target()) {
+      auto it = labels.find(*l.target());
+      CHECK(it != labels.end()) << "Undefined label: " << *l.target();
+      instr.Soperand(it->second);
+    }
+    instr.AssembleInto(&prg);
+  }
+  return prg;
+}
+
+LabeledProgram BranchIf(uint16_t alu_index, const std::string& label) {
+  return {Instr()
+              .Src(Unit::UNIT_ALU_RESULT)
+              .Si(alu_index)
+              .Dst(Unit::UNIT_NONE)
+              .Di(0),
+          LabeledInstr::WithTarget(Instr()
+                                       .Src(Unit::UNIT_ABS_OPERAND)
+                                       .Dst(Unit::UNIT_PC_IF)
+                                       .Di(alu_index),
+                                   label)};
+}
+
+std::ostream& operator<<(std::ostream& os, const ProgramDifference& d) {
+  auto print = [&os](const std::optional<Instr>& instr) {
+    if (instr)
+      os << Instr(*instr).Comment("");
+    else
+      os << "(none)";
+  };
+  os << d.index << ": ";
+  print(d.a);
+  os << " -> ";
+  print(d.b);
+  return os;
+}
+
+std::ostream& operator<<(std::ostream& os, const RoundtripError& e) {
+  os << std::hex << std::setw(4) << std::setfill('0') << e.pos << std::dec
+     << std::setfill(' ') << ": ";
+  if (e.decode_error)
+    return os << *e.decode_error;
+  if (e.assemble_error)
+    return os << *e.assemble_error;
+  return os << "reassembles to different words";
+}
+
+bool VerifyRoundtrip(const std::vector<uint32_t>& words,
+                     RoundtripError* error) {
+  size_t pos = 0;
+  std::vector<uint32_t> reassembled;
+  while (pos < words.size()) {
+    Instr instr;
+    size_t len;
+    DecodeError decode_error;
+    if (!Instr::TryDecode(words, pos, &instr, &len, &decode_error)) {
+      *error = {pos, decode_error, std::nullopt};
+      return false;
+    }
+    AssembleError assemble_error;
+    if (!instr.TryAssemble(&reassembled, &assemble_error)) {
+      *error = {pos, std::nullopt, assemble_error};
+      return false;
+    }
+    if (reassembled.size() != len ||
+        !std::equal(reassembled.begin(), reassembled.end(),
+                    words.begin() + pos)) {
+      *error = {pos, std::nullopt, std::nullopt};
+      return false;
+    }
+    pos += len;
+  }
+  return true;
+}
+
+std::string ToCppSource(const Program& program) {
+  std::ostringstream os;
+  const char* sep = "";
+  for (const Instr& instr : program) {
+    os << sep << "Instr().Src(Unit::" << instr.src_unit() << ")";
+    if (instr.si())
+      os << ".Si(" << instr.si() << ")";
+    if (instr.soperand())
+      os << ".Soperand(0x" << std::hex << *instr.soperand() << std::dec << ")";
+    os << ".Dst(Unit::" << instr.dst_unit() << ")";
+    if (instr.di())
+      os << ".Di(" << instr.di() << ")";
+    if (instr.doperand())
+      os << ".Doperand(0x" << std::hex << *instr.doperand() << std::dec << ")";
+    sep = ",\n";
+  }
+  return os.str();
+}
+
+std::vector<ProgramDifference> DiffPrograms(const Program& a,
+                                            const Program& b) {
+  std::vector<ProgramDifference> differences;
+  for (size_t i = 0; i < std::max(a.size(), b.size()); i++) {
+    std::optional<Instr> from, to;
+    if (i < a.size())
+      from = a.begin()[i];
+    if (i < b.size())
+      to = b.begin()[i];
+    if (from != to)
+      differences.push_back({i, from, to});
+  }
+  return differences;
+}
@@ -3,7 +3,11 @@
 #include <glog/logging.h>
 
 #include <cstdint>
+#include <functional>
+#include <initializer_list>
 #include <optional>
+#include <ostream>
+#include <string>
 #include <vector>
 
 enum class ALUOp {
@@ -14,6 +18,8 @@ enum class ALUOp {
   ALU_DIV = 0x004,
   ALU_MOD = 0x005,
   ALU_EQL = 0x006,
+  // Shifts use only the low 5 bits of the right operand, so shifting by 32
+  // is shifting by 0 and by 33 is shifting by 1.
   ALU_SL = 0x007,
   ALU_SR = 0x008,
   ALU_SRA = 0x009,
@@ -21,11 +27,55 @@ enum class ALUOp {
   ALU_AND = 0x00b,
   ALU_OR = 0x00c,
   ALU_XOR = 0x00d,
+  // Comparisons are unsigned: 0xffffffff is greater than 1.
   ALU_GT = 0x00e,
-  ALU_LT = 0x00f
+  ALU_LT = 0x00f,
+  // Upper 32 bits of the unsigned 64-bit product; ALU_MUL gives the lower
+  // 32. Non-zero exactly when ALU_MUL of the same operands overflowed.
+  ALU_MULHU = 0x010,
+  // Signed, on the left operand only. Both wrap, so ALU_ABS and ALU_NEG of
+  // 0x80000000 give 0x80000000.
+  ALU_ABS = 0x011,
+  ALU_NEG = 0x012,
+  // Leading and trailing zero bits of the left operand; 32 for 0.
+  ALU_CLZ = 0x013,
+  ALU_CTZ = 0x014,
+  // Set bits in the left operand.
+  ALU_POPCNT = 0x015,
+  // Signed division, truncating toward zero as in C: -7 / 2 is -3 with
+  // remainder -1, where ALU_DIV and ALU_MOD see 0xfffffff9 / 2. Dividing
+  // by zero gives 0; 0x80000000 / -1 wraps to 0x80000000, remainder 0.
+  ALU_SDIV = 0x016,
+  ALU_SMOD = 0x017,
 };
 
+// Every ALUOp, in opcode order, for tests that want to cover them all.
+constexpr ALUOp kAllALUOps[] = {
+    ALUOp::ALU_NOP, ALUOp::ALU_ADD, ALUOp::ALU_SUB, ALUOp::ALU_MUL,
+    ALUOp::ALU_DIV, ALUOp::ALU_MOD, ALUOp::ALU_EQL, ALUOp::ALU_SL,
+    ALUOp::ALU_SR,  ALUOp::ALU_SRA, ALUOp::ALU_NOT, ALUOp::ALU_AND,
+    ALUOp::ALU_OR,  ALUOp::ALU_XOR, ALUOp::ALU_GT,  ALUOp::ALU_LT,
+    ALUOp::ALU_MULHU, ALUOp::ALU_ABS, ALUOp::ALU_NEG, ALUOp::ALU_CLZ,
+    ALUOp::ALU_CTZ,   ALUOp::ALU_POPCNT, ALUOp::ALU_SDIV, ALUOp::ALU_SMOD,
+};
+
+// Whether swapping an op's operands leaves its result unchanged, going by
+// alu_unit.sv. ALU_AND and ALU_OR are logical rather than bitwise there,
+// but still commute.
+bool IsCommutative(ALUOp op);
+
+// Whether an op only looks at its left operand. ALU_XOR is a reduction of
+// the left operand in alu_unit.sv, and ALU_NOP ignores both.
+bool IsUnary(ALUOp op);
+
+// A right operand `e` with op(x, e) == x for every x, going by
+// alu_unit.sv; for commutative ops it's an identity on either side.
+// Logical ALU_AND and ALU_OR have none, since they reduce x to 0 or 1.
+std::optional<uint32_t> IdentityElement(ALUOp op);
+
 enum class Unit {
+  // As a source, reads as 0; as a destination, discards the value. A move
+  // from UNIT_NONE to UNIT_NONE is the no-op, Instr::Nop().
   UNIT_NONE = 0,
   UNIT_STACK_PUSH_POP = 1,
   UNIT_STACK_INDEX = 2,
@@ -33,6 +83,10 @@ enum class Unit {
   UNIT_ALU_LEFT = 4,
   UNIT_ALU_RIGHT = 5,
   UNIT_ALU_OPERATOR = 6,
+  // Computed when read, from the inputs and operator set at that moment;
+  // writing an input or the operator computes nothing by itself. A value
+  // already moved out of here doesn't change when the inputs do, but the
+  // next read sees them. UNIT_PC_IF tests the result as of the last read.
   UNIT_ALU_RESULT = 7,
   UNIT_MEMORY_IMMEDIATE = 8,
   UNIT_MEMORY_OPERAND = 9,
@@ -40,34 +94,400 @@ enum class Unit {
   UNIT_ABS_IMMEDIATE = 11,
   UNIT_ABS_OPERAND = 12,
   UNIT_REGISTER_POINTER = 13,
+  // Writes PC only if the last result of ALU N is non-zero.
+  UNIT_PC_IF = 14,
+};
+
+std::ostream& operator<<(std::ostream& os, ALUOp op);
+std::ostream& operator<<(std::ostream& os, Unit u);
+
+// Number of registers and ALUs the core has, i.e. the valid immediates for
+// UNIT_REGISTER and the UNIT_ALU_* units. Must match NUM_REGISTERS and
+// NUM_ALUS in execute.sv. There are no stacks yet.
+constexpr int kNumRegisters = 32;
+constexpr int kNumAlus = 8;
+
+// Layout of an opcode word, as decoded by decoder.sv. Each field is
+// (word >> shift) & mask.
+constexpr uint32_t kSrcUnitShift = 0;
+constexpr uint32_t kSrcUnitMask = 0xf;
+constexpr uint32_t kSiShift = 4;
+constexpr uint32_t kSiMask = 0xfff;
+constexpr uint32_t kDstUnitShift = 16;
+constexpr uint32_t kDstUnitMask = 0xf;
+constexpr uint32_t kDiShift = 20;
+constexpr uint32_t kDiMask = 0xfff;
+
+// Legal directions for each unit, as implemented by execute.sv:
+//
+//   unit                    source  destination
+//   UNIT_NONE                 yes       yes
+//   UNIT_STACK_PUSH_POP        -         -       (not implemented)
+//   UNIT_STACK_INDEX           -         -       (not implemented)
+//   UNIT_REGISTER             yes       yes
+//   UNIT_ALU_LEFT             yes       yes
+//   UNIT_ALU_RIGHT            yes       yes
+//   UNIT_ALU_OPERATOR         yes       yes
+//   UNIT_ALU_RESULT           yes        -
+//   UNIT_MEMORY_IMMEDIATE     yes       yes
+//   UNIT_MEMORY_OPERAND       yes       yes
+//   UNIT_PC                   yes       yes
+//   UNIT_ABS_IMMEDIATE        yes        -
+//   UNIT_ABS_OPERAND          yes        -
+//   UNIT_REGISTER_POINTER     yes       yes
+//   UNIT_PC_IF                 -        yes
+//
+// Anything else is accepted by the decoder but silently does nothing
+// useful.
+//
+// A move finishes reading its source before it starts writing its
+// destination, and moves run strictly one after another. So a memory move
+// whose source and destination are the same word leaves it unchanged, and
+// each move sees everything the moves before it wrote.
+bool IsValidSource(Unit u);
+bool IsValidDest(Unit u);
+
+// Which side of a move a unit appears on.
+enum class Role { SOURCE, DESTINATION };
+
+// Why an instruction can't be assembled.
+struct AssembleError {
+  enum class Kind {
+    // The unit can't be used in this role; see IsValidSource/IsValidDest.
+    ILLEGAL_DIRECTION,
+    // The unit takes a 32-bit operand but none was given.
+    MISSING_OPERAND,
+  };
+  Kind kind;
+  Unit unit;
+  Role role;
+};
+std::ostream& operator<<(std::ostream& os, const AssembleError& e);
+
+// Why words couldn't be decoded into an instruction.
+struct DecodeError {
+  enum class Kind {
+    // The opcode word's unit takes an operand, but the words ran out
+    // before it.
+    TRUNCATED_OPERAND,
+  };
+  Kind kind;
+  Unit unit;
+  Role role;
 };
+std::ostream& operator<<(std::ostream& os, const DecodeError& e);
 
 class Instr;
-using Program = std::vector<Instr>;
+class LabeledInstr;
+class Program;
+using LabeledProgram = std::vector<LabeledInstr>;
 class Instr {
  public:
+  // Pseudo-instruction marking the address of the instruction that follows
+  // it in a LabeledProgram.
+  static LabeledInstr Label(const std::string& name);
+
+  // Unconditional jump: moves the address of `label` into UNIT_PC.
+  static LabeledInstr Jump(const std::string& label);
+
+  // Computed jump: moves register `reg` into UNIT_PC, e.g. after loading
+  // it from a jump table. Like every PC value, the register holds a word
+  // address in instruction memory, not a byte address.
+  static Instr JumpReg(short reg);
+
+  // Indirect memory access through register `reg`, which holds a data
+  // memory address: move the word at that address to `dst` (immediate
+  // `di`), or move `src` (immediate `si`) to that address.
+  static Instr LoadViaPointer(short reg, Unit dst, short di);
+  static Instr StoreViaPointer(Unit src, short si, short reg);
+
+  // Move the operator currently set on ALU `alu_index` to `dst`
+  // (immediate `di`), as its ALUOp value.
+  static Instr ReadAluOp(short alu_index, Unit dst, short di);
+
+  // Move the PC to `dst` (immediate `di`). The value read is the address
+  // of the instruction after this one, not of this move itself.
+  static Instr ReadPc(Unit dst, short di);
+
+  // UNIT_NONE -> UNIT_NONE, which the core completes without doing
+  // anything. Also what an all-zero word decodes to.
+  static Instr Nop();
+
+  // Decode the instruction starting at words[pos], along with any operand
+  // words that follow it. Sets *len to the number of words consumed.
+  static Instr Decode(const std::vector<uint32_t>& words,
+                      size_t pos,
+                      size_t* len);
+
+  // Like Decode(), but reports missing operand words through `error`
+  // rather than failing a CHECK. `pos` must still be within `words`.
+  static bool TryDecode(const std::vector<uint32_t>& words,
+                        size_t pos,
+                        Instr* instr,
+                        size_t* len,
+                        DecodeError* error);
+
   std::vector<uint32_t> assemble() const;
 
+  // Like assemble(), but reports problems through `error` rather than
+  // failing a CHECK. Returns false if the instruction can't be assembled.
+  bool TryAssemble(std::vector<uint32_t>* prg, AssembleError* error) const;
+
+  // Append the instruction's words to `buf`, saving assemble()'s
+  // allocation when assembling many instructions into one buffer. The Try
+  // version appends nothing if the instruction can't be assembled.
+  void AssembleInto(std::vector<uint32_t>* buf) const;
+  bool TryAssembleInto(std::vector<uint32_t>* buf, AssembleError* error) const;
+
   bool UsesSoperand() const;
   bool UsesDoperand() const;
 
+  // Number of words assemble() will produce, without assembling.
+  size_t LenWords() const { return 1 + UsesSoperand() + UsesDoperand(); }
+
   Instr& Src(Unit u);
   Instr& Dst(Unit u);
+  // CHECK-fail if `i` doesn't fit in the 12-bit immediate field, rather
+  // than truncating it.
   Instr& Si(short i);
   Instr& Di(short i);
+  // Like Si() and Di(), but leave the instruction alone and return false
+  // if `i` doesn't fit.
+  bool TrySi(int i);
+  bool TryDi(int i);
 
   Instr& Soperand(uint32_t o);
 
   Instr& Doperand(uint32_t o);
 
+  // A note printed after the move by operator<<, e.g. the source line a
+  // compiler generated it from. It isn't encoded, so assemble() output
+  // and operator== ignore it.
+  Instr& Comment(const std::string& text);
+
+  // Read back what the setters above stored. Operands are only present if
+  // they were set or decoded.
+  Unit src_unit() const { return (Unit)op_.src_unit; }
+  Unit dst_unit() const { return (Unit)op_.dst_unit; }
+  unsigned short si() const { return op_.si; }
+  unsigned short di() const { return op_.di; }
+  const std::optional<uint32_t>& soperand() const { return soperand_; }
+  const std::optional<uint32_t>& doperand() const { return doperand_; }
+  const std::string& comment() const { return comment_; }
+
  private:
+
   struct OpFormat {
     unsigned short src_unit : 4;
     unsigned short si : 12;
     unsigned dst_unit : 4;
     unsigned short di : 12;
   };
-  OpFormat op_;
+  OpFormat op_{};
   std::optional<uint32_t> soperand_;
   std::optional<uint32_t> doperand_;
+  std::string comment_;
+};
+
+// Instructions are equal if their units, immediates and operands all match.
+// An operand that was set never equals one that wasn't.
+bool operator==(const Instr& a, const Instr& b);
+bool operator!=(const Instr& a, const Instr& b);
+
+namespace std {
+// Hashes the same fields operator== compares, so Instr can key an
+// unordered_map. Unit and ALUOp get std::hash as enums already.
+template <>
+struct hash<Instr> {
+  size_t operator()(const Instr& instr) const;
+};
+}  // namespace std
+
+// Disassembly in the "dst := src" notation used by assembler.py, e.g.
+// "R00 := #29a" or "*(07b) := ALU0:RESULT".
+std::ostream& operator<<(std::ostream& os, const Instr& instr);
+
+// Estimated clock cycles the core takes to fetch and execute `instr`,
+// going by the sequencer and execute state machines with memory that
+// answers on the cycle after a request. Meant for reasoning about program
+// timing without running the model, not as an exact count.
+int CyclesFor(const Instr& instr);
+
+// Why a line of assembly text couldn't be parsed.
+struct ParseError {
+  // 1-based line number and the text of that line.
+  int line;
+  std::string text;
+  std::string message;
 };
+
+// Parse assembly text in the notation operator<< produces, one move per
+// line. Anything after a ';' is a comment, and one following a move is kept
+// as that Instr's comment(). Immediate and operand forms of
+// memory and absolute units are told apart by the number of hex digits:
+// up to three is an immediate, e.g. "#29a", more is an operand, e.g.
+// "#0000029a".
+bool ParseAsm(const std::string& text, Program* program, ParseError* error);
+
+// Byte order of the words in a program file. The core fetches whole 32-bit
+// words over instr_data_read_i, so the order only matters on disk; a file
+// must be read back the way it was written. Little-endian is the default.
+enum class Endianness { LITTLE, BIG };
+
+// Programs as files of 32-bit words. Reading returns nothing if the file
+// can't be read or isn't a whole number of words.
+std::optional<std::vector<uint32_t>> ReadProgramBinary(
+    const std::string& path,
+    Endianness order = Endianness::LITTLE);
+bool WriteProgramBinary(const std::string& path,
+                        const std::vector<uint32_t>& words,
+                        Endianness order = Endianness::LITTLE);
+
+// A straight-line sequence of moves, with shorthands for building the
+// common ones. Instr stays available for anything the shorthands don't
+// cover.
+class Program {
+ public:
+  Program() = default;
+  Program(std::initializer_list<Instr> instrs) : instrs_(instrs) {}
+
+  Program& Add(const Instr& instr);
+
+  // Move from `src` (immediate `si`) to `dst` (immediate `di`). Only for
+  // units that don't take an operand.
+  Program& Mov(Unit src, short si, Unit dst, short di);
+
+  // Load ALU `idx`'s operands from registers `left` and `right` and set its
+  // operator to `op`. The result can then be read from UNIT_ALU_RESULT.
+  Program& Alu(short left, short right, ALUOp op, short idx);
+
+  // Put the 32-bit `value` in register `reg` with the fewest words: a
+  // single-word immediate move if it fits in si, else an ABS_OPERAND move.
+  // Operands are a full word, so no ALU shift/or sequence is ever needed.
+  Program& LoadConstant(uint32_t value, short reg);
+
+  std::vector<uint32_t> assemble() const;
+
+  // Like assemble(), also filling `offsets` with the word offset each
+  // instruction starts at, for mapping addresses back to instructions;
+  // see InstrIndexAt().
+  std::vector<uint32_t> AssembleWithMap(std::vector<size_t>* offsets) const;
+
+  // Number of words assemble() will produce.
+  size_t LenWords() const;
+
+  // Whether the assembled program fits in `words` words of memory.
+  bool FitsIn(size_t words) const { return LenWords() <= words; }
+
+  // Sum of CyclesFor() over every instruction.
+  int TotalCycles() const;
+
+  size_t size() const { return instrs_.size(); }
+  std::vector<Instr>::const_iterator begin() const { return instrs_.begin(); }
+  std::vector<Instr>::const_iterator end() const { return instrs_.end(); }
+
+ private:
+  std::vector<Instr> instrs_;
+};
+
+// Index of the instruction whose words include `word`, an offset into the
+// program, given the offsets from Program::AssembleWithMap(). Operand
+// words belong to the instruction before them. Nothing if `word` is past
+// the last instruction's opcode word and operands.
+std::optional<size_t> InstrIndexAt(const Program& program,
+                                   const std::vector<size_t>& offsets,
+                                   uint32_t word);
+
+// A position at which two programs differ. Past the end of the shorter
+// program, that side is empty.
+struct ProgramDifference {
+  size_t index;
+  std::optional<Instr> a;
+  std::optional<Instr> b;
+};
+// e.g. "3: R01 := #001 -> R01 := #002", with "(none)" for a missing side.
+std::ostream& operator<<(std::ostream& os, const ProgramDifference& d);
+
+// Compare two programs move by move, using Instr's operator== so comments
+// don't count. Moves aren't realigned after an insertion or deletion;
+// everything after it shows up as a difference.
+std::vector<ProgramDifference> DiffPrograms(const Program& a,
+                                            const Program& b);
+
+// Why words didn't come back unchanged from decoding and reassembling.
+struct RoundtripError {
+  // Word position of the instruction at fault.
+  size_t pos;
+  // At most one is set. With neither, the instruction reassembled to
+  // different words.
+  std::optional<DecodeError> decode_error;
+  std::optional<AssembleError> assemble_error;
+};
+// e.g. "0003: UNIT_ABS_IMMEDIATE can't be used as a destination".
+std::ostream& operator<<(std::ostream& os, const RoundtripError& e);
+
+// Decode `words` an instruction at a time and reassemble each, checking
+// the words come back exactly; keeps Decode() and assemble() inverses of
+// each other. The decoder accepts moves the assembler refuses, such as
+// writes to UNIT_ABS_IMMEDIATE, so those fail too.
+bool VerifyRoundtrip(const std::vector<uint32_t>& words,
+                     RoundtripError* error);
+
+// `program` as C++ that rebuilds it: one Instr() builder chain per move,
+// comma separated, to paste between the braces of a Program or Load()
+// initializer list. For turning a failing randomized run into a fixed
+// test. Comments aren't included, and immediates of 0 are left out.
+std::string ToCppSource(const Program& program);
+
+// A Program with each Instr field in its own array, for analyses that scan
+// one field across a large program. Entry i of every array belongs to the
+// program's i'th instruction.
+struct ProgramSoA {
+  ProgramSoA() = default;
+  explicit ProgramSoA(const Program& program);
+
+  Program ToProgram() const;
+  size_t size() const { return src_units.size(); }
+
+  std::vector<Unit> src_units;
+  std::vector<unsigned short> sis;
+  std::vector<Unit> dst_units;
+  std::vector<unsigned short> dis;
+  std::vector<std::optional<uint32_t>> soperands;
+  std::vector<std::optional<uint32_t>> doperands;
+};
+
+// An entry in a LabeledProgram: either a label definition, or an
+// instruction whose source operand may name a label to be filled in with
+// that label's instruction word offset at assembly time.
+class LabeledInstr {
+ public:
+  LabeledInstr(const Instr& instr) : instr_(instr) {}
+
+  // `instr` with its source operand set to the address of `label`. The
+  // instruction's source unit must take an operand.
+  static LabeledInstr WithTarget(const Instr& instr, const std::string& label);
+
+  const Instr& instr() const { return instr_; }
+  const std::optional<std::string>& label() const { return label_; }
+  const std::optional<std::string>& target() const { return target_; }
+
+ private:
+  friend class Instr;
+  LabeledInstr() = default;
+
+  Instr instr_;
+  std::optional<std::string> label_;
+  std::optional<std::string> target_;
+};
+
+// Two-pass assembly: first lay out instructions to find each label's word
+// offset, then assemble with label references resolved. Label addresses
+// are relative to `base`, the word address the program will be loaded at.
+std::vector<uint32_t> AssembleLabeled(const LabeledProgram& program,
+                                      uint32_t base = 0);
+
+// Jump to `label` if the result of ALU `alu_index` is non-zero, e.g. after
+// setting it up with ALU_LT or ALU_EQL. Reads the result once to latch it,
+// then guards the PC write on it via UNIT_PC_IF.
+LabeledProgram BranchIf(uint16_t alu_index, const std::string& label);
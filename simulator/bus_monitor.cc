@@ -0,0 +1,60 @@
+#include "bus_monitor.h"
+
+namespace {
+
+bool SameRequest(const BusSample& a, const BusSample& b) {
+  return a.addr == b.addr && a.wstrb == b.wstrb &&
+         (a.wstrb == 0 || a.write_data == b.write_data);
+}
+
+}  // namespace
+
+std::ostream& operator<<(std::ostream& os, const ProtocolViolation& v) {
+  switch (v.kind) {
+    case ProtocolViolation::Kind::VALID_DROPPED:
+      os << "valid dropped";
+      break;
+    case ProtocolViolation::Kind::ADDR_CHANGED:
+      os << "address changed";
+      break;
+    case ProtocolViolation::Kind::DATA_CHANGED:
+      os << "write data changed";
+      break;
+  }
+  return os << " before ready, request to " << v.addr << " at cycle "
+            << v.cycle;
+}
+
+void BusMonitor::Observe(int cycle, const BusSample& sample) {
+  if (pending_) {
+    if (sample.ready) {
+      pending_ = false;
+    } else if (!sample.valid) {
+      Report(ProtocolViolation::Kind::VALID_DROPPED, cycle);
+      pending_ = false;
+    } else if (sample.addr != request_.addr) {
+      Report(ProtocolViolation::Kind::ADDR_CHANGED, cycle);
+      request_ = sample;
+    } else if (!SameRequest(sample, request_)) {
+      Report(ProtocolViolation::Kind::DATA_CHANGED, cycle);
+      request_ = sample;
+    }
+  }
+  if (!pending_ && sample.valid &&
+      (!last_valid_ || !SameRequest(sample, request_))) {
+    pending_ = true;
+    request_ = sample;
+  }
+  last_valid_ = sample.valid;
+}
+
+void BusMonitor::Clear() {
+  pending_ = false;
+  request_ = {};
+  last_valid_ = false;
+  violations_.clear();
+}
+
+void BusMonitor::Report(ProtocolViolation::Kind kind, int cycle) {
+  violations_.push_back({kind, cycle, request_.addr});
+}
@@ -0,0 +1,61 @@
+#pragma once
+
+#include <cstdint>
+#include <ostream>
+#include <vector>
+
+// The master's side of a bus_if, plus the ready line it sees, as of one
+// cycle.
+struct BusSample {
+  bool valid;
+  bool ready;
+  uint32_t addr;
+  uint32_t write_data;
+  uint8_t wstrb;
+};
+
+// A way the master broke the valid/ready handshake.
+struct ProtocolViolation {
+  enum class Kind {
+    // Valid dropped before ready was seen.
+    VALID_DROPPED,
+    // The address changed while waiting for ready.
+    ADDR_CHANGED,
+    // The write strobes or write data changed while waiting for ready.
+    DATA_CHANGED,
+  };
+  Kind kind;
+  int cycle;
+  // Address of the request that was interrupted.
+  uint32_t addr;
+};
+std::ostream& operator<<(std::ostream& os, const ProtocolViolation& v);
+
+// Checks a master's requests against the handshake RAMSim implements.
+//
+// A request starts on the cycle valid rises, or on a cycle valid is held
+// but the address, strobes or write data change. From then on the master
+// must hold all of them steady until it has seen ready; the ready line on
+// the starting cycle itself is left over from the previous request and is
+// ignored. Once a request completes the master may keep valid high.
+//
+// Feed it the bus state before the slave responds, once per bus cycle.
+class BusMonitor {
+ public:
+  void Observe(int cycle, const BusSample& sample);
+  void Clear();
+
+  const std::vector<ProtocolViolation>& violations() const {
+    return violations_;
+  }
+
+ private:
+  void Report(ProtocolViolation::Kind kind, int cycle);
+
+  // Whether a request is waiting on ready, and that request (or the last
+  // completed one, if not).
+  bool pending_ = false;
+  BusSample request_{};
+  bool last_valid_ = false;
+  std::vector<ProtocolViolation> violations_;
+};
@@ -5,7 +5,7 @@
 
 void ClockGenerator::Step(VerilatedFstC* trace) {
   // Run for some clock cycles in reset before booting...
-  if (step_ > reset_steps_ && *reset_) {
+  if (step_ > reset_until_ && *reset_) {
     LOG(INFO) << "Releasing reset";
     *reset_ = 0;
   }
@@ -26,3 +26,8 @@ void ClockGenerator::Step(VerilatedFstC* trace) {
   }
   step_++;
 }
+
+void ClockGenerator::Reset() {
+  *reset_ = 1;
+  reset_until_ = step_ + reset_steps_;
+}
@@ -2,7 +2,7 @@
 
 #include <verilated.h>
 
-class VerilatedVcdC;
+class VerilatedFstC;
 
 class ClockGenerator {
  public:
@@ -12,6 +12,7 @@ class ClockGenerator {
                  CData* clk_bus)
       : divisor_(divisor),
         reset_steps_(reset_cycles * divisor),
+        reset_until_(reset_steps_),
         reset_(reset),
         clk_bus_(clk_bus) {}
 
@@ -19,6 +20,10 @@ class ClockGenerator {
 
   void Step(VerilatedFstC* trace = nullptr);
 
+  // Assert reset again, releasing it after the configured number of reset
+  // cycles have elapsed from now.
+  void Reset();
+
   bool Bus() const { return posedge_bus_; }
   const int step() const { return step_; }
   const int cycles() const { return cycle_; }
@@ -26,6 +31,7 @@ class ClockGenerator {
  private:
   const int divisor_;
   const int reset_steps_;
+  int reset_until_;
 
   CData* reset_;
   CData* clk_bus_;
@@ -0,0 +1,24 @@
+#include "debugger.h"
+
+std::optional<MachineState> Debugger::ContinueUntilBreak(int max_cycles) {
+  int start_clk = harness_->clk().cycles();
+  while (harness_->clk().cycles() - start_clk < max_cycles) {
+    StepResult result = harness_->Step();
+    last_done_ = result.instr_done;
+    if (result.instr_fetch && breakpoints_.count(*result.instr_fetch))
+      return harness_->DumpState();
+  }
+  return std::nullopt;
+}
+
+std::optional<MachineState> Debugger::StepInstruction(int max_cycles) {
+  int start_clk = harness_->clk().cycles();
+  while (harness_->clk().cycles() - start_clk < max_cycles) {
+    StepResult result = harness_->Step();
+    bool done_rose = result.instr_done && !last_done_;
+    last_done_ = result.instr_done;
+    if (done_rose)
+      return harness_->DumpState();
+  }
+  return std::nullopt;
+}
@@ -0,0 +1,30 @@
+#pragma once
+
+#include <optional>
+#include <set>
+
+#include "tta_harness.h"
+
+// Breakpoints and instruction-level stepping on top of a TTAHarness. Every
+// stop reports the harness's MachineState at that point.
+class Debugger {
+ public:
+  explicit Debugger(TTAHarness* harness) : harness_(harness) {}
+
+  // Stop when the opcode at `pc` starts being fetched, before it runs.
+  void SetBreakpoint(uint32_t pc) { breakpoints_.insert(pc); }
+  void ClearBreakpoint(uint32_t pc) { breakpoints_.erase(pc); }
+
+  // Run until a breakpoint is hit. Returns nothing if max_cycles pass
+  // first.
+  std::optional<MachineState> ContinueUntilBreak(int max_cycles = 10000);
+
+  // Run until the current move completes, i.e. until instr_done_o next
+  // rises. Returns nothing if max_cycles pass first.
+  std::optional<MachineState> StepInstruction(int max_cycles = 1000);
+
+ private:
+  TTAHarness* harness_;
+  std::set<uint32_t> breakpoints_;
+  bool last_done_ = false;
+};
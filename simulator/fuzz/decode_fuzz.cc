@@ -0,0 +1,41 @@
+// libFuzzer target for Instr::Decode. Treats the input as a stream of
+// little-endian words and decodes it from the start, checking that every
+// instruction that decodes to something legal assembles back to exactly
+// the words it came from.
+
+#include <glog/logging.h>
+
+#include <algorithm>
+#include <cstdint>
+#include <cstring>
+#include <vector>
+
+#include "assembler.h"
+
+extern "C" int LLVMFuzzerTestOneInput(const uint8_t* data, size_t size) {
+  std::vector<uint32_t> words(size / sizeof(uint32_t));
+  memcpy(words.data(), data, words.size() * sizeof(uint32_t));
+
+  size_t pos = 0;
+  while (pos < words.size()) {
+    Instr instr;
+    size_t len;
+    DecodeError decode_error;
+    if (!Instr::TryDecode(words, pos, &instr, &len, &decode_error)) {
+      // Only the last instruction can be short of operand words.
+      CHECK(decode_error.kind == DecodeError::Kind::TRUNCATED_OPERAND);
+      CHECK_GE(pos + 2, words.size());
+      break;
+    }
+    std::vector<uint32_t> assembled;
+    AssembleError error;
+    if (instr.TryAssemble(&assembled, &error)) {
+      CHECK_EQ(assembled.size(), len) << instr;
+      CHECK(std::equal(assembled.begin(), assembled.end(),
+                       words.begin() + pos))
+          << instr;
+    }
+    pos += len;
+  }
+  return 0;
+}
@@ -0,0 +1,17 @@
+#include "memory_backend.h"
+
+uint32_t MergeBytes(uint32_t word, uint32_t data, uint8_t wstrb) {
+  for (int i = 0; i < 4; i++) {
+    if (wstrb & (1 << i)) {
+      uint32_t mask = 0xffU << (i * 8);
+      word = (word & ~mask) | (data & mask);
+    }
+  }
+  return word;
+}
+
+void LoadInto(const Program& program, MemoryBackend* backend, uint32_t base) {
+  uint32_t addr = base;
+  for (uint32_t word : program.assemble())
+    backend->Write(addr++, word, 0xf);
+}
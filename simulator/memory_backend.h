@@ -0,0 +1,91 @@
+#pragma once
+
+#include <cstddef>
+#include <cstdint>
+#include <functional>
+#include <string>
+#include <utility>
+#include <vector>
+
+#include "assembler.h"
+#include "clock_gen.h"
+
+// A device that can be mapped into a region of a RAMSim's address space.
+// Addresses passed in are relative to the start of the region.
+class MemoryBackend {
+ public:
+  virtual ~MemoryBackend() = default;
+
+  virtual uint32_t Read(uint32_t addr) = 0;
+
+  // Write the bytes of `data` selected by `wstrb`.
+  virtual void Write(uint32_t addr, uint32_t data, uint8_t wstrb) = 0;
+};
+
+// Apply the bytes of `data` selected by `wstrb` to `word`.
+uint32_t MergeBytes(uint32_t word, uint32_t data, uint8_t wstrb);
+
+// Assemble `program` and write it word by word to `backend`, starting at
+// `base`, e.g. to fill a device mapped as instruction memory.
+void LoadInto(const Program& program, MemoryBackend* backend, uint32_t base);
+
+// Plain word-addressed RAM.
+class RAMBackend : public MemoryBackend {
+ public:
+  explicit RAMBackend(size_t size) : mem_(size) {}
+
+  uint32_t Read(uint32_t addr) override { return mem_[addr]; }
+  void Write(uint32_t addr, uint32_t data, uint8_t wstrb) override {
+    mem_[addr] = MergeBytes(mem_[addr], data, wstrb);
+  }
+
+  std::vector<uint32_t>& mem() { return mem_; }
+
+ private:
+  std::vector<uint32_t> mem_;
+};
+
+// Read-only device returning the clock's cycle count, for timing code from
+// inside a program. Writes are ignored.
+class CycleCounterBackend : public MemoryBackend {
+ public:
+  explicit CycleCounterBackend(const ClockGenerator& clock) : clock_(clock) {}
+
+  uint32_t Read(uint32_t addr) override { return clock_.cycles(); }
+  void Write(uint32_t addr, uint32_t data, uint8_t wstrb) override {}
+
+ private:
+  const ClockGenerator& clock_;
+};
+
+// Read-only device whose words come from a function of the address, e.g.
+// instructions generated on demand. RAMSim reads on every cycle of an
+// access, so the function may see the same address several times. Writes
+// are ignored.
+class FunctionBackend : public MemoryBackend {
+ public:
+  explicit FunctionBackend(std::function<uint32_t(uint32_t)> read)
+      : read_(std::move(read)) {}
+
+  uint32_t Read(uint32_t addr) override { return read_(addr); }
+  void Write(uint32_t addr, uint32_t data, uint8_t wstrb) override {}
+
+ private:
+  std::function<uint32_t(uint32_t)> read_;
+};
+
+// Output-only serial port: each write to its data register, at offset 0,
+// appends the low byte to output(). Reads return 0.
+class UARTBackend : public MemoryBackend {
+ public:
+  uint32_t Read(uint32_t addr) override { return 0; }
+  void Write(uint32_t addr, uint32_t data, uint8_t wstrb) override {
+    if (addr == 0 && (wstrb & 1))
+      output_.push_back((char)data);
+  }
+
+  const std::string& output() const { return output_; }
+
+ private:
+  std::string output_;
+};
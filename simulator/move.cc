@@ -0,0 +1,158 @@
+#include "move.h"
+
+#include <type_traits>
+
+namespace {
+
+Move::AluInput::Port PortFor(Unit u) {
+  switch (u) {
+    case Unit::UNIT_ALU_LEFT:
+      return Move::AluInput::Port::LEFT;
+    case Unit::UNIT_ALU_RIGHT:
+      return Move::AluInput::Port::RIGHT;
+    default:
+      return Move::AluInput::Port::OPERATOR;
+  }
+}
+
+Unit UnitFor(Move::AluInput::Port port) {
+  switch (port) {
+    case Move::AluInput::Port::LEFT:
+      return Unit::UNIT_ALU_LEFT;
+    case Move::AluInput::Port::RIGHT:
+      return Unit::UNIT_ALU_RIGHT;
+    case Move::AluInput::Port::OPERATOR:
+      return Unit::UNIT_ALU_OPERATOR;
+  }
+  return Unit::UNIT_NONE;
+}
+
+// The unit, immediate and operand an alternative of either side encodes
+// as.
+struct Encoded {
+  Unit unit;
+  unsigned short immediate = 0;
+  std::optional<uint32_t> operand;
+};
+
+template <typename T>
+Encoded Encode(const T& side) {
+  if constexpr (std::is_same_v<T, Move::Nothing>) {
+    return {Unit::UNIT_NONE};
+  } else if constexpr (std::is_same_v<T, Move::Register>) {
+    return {Unit::UNIT_REGISTER, side.index};
+  } else if constexpr (std::is_same_v<T, Move::RegisterPointer>) {
+    return {Unit::UNIT_REGISTER_POINTER, side.reg};
+  } else if constexpr (std::is_same_v<T, Move::AluInput>) {
+    return {UnitFor(side.port), side.alu};
+  } else if constexpr (std::is_same_v<T, Move::AluResult>) {
+    return {Unit::UNIT_ALU_RESULT, side.alu};
+  } else if constexpr (std::is_same_v<T, Move::MemoryImmediate>) {
+    return {Unit::UNIT_MEMORY_IMMEDIATE, side.addr};
+  } else if constexpr (std::is_same_v<T, Move::MemoryOperand>) {
+    return {Unit::UNIT_MEMORY_OPERAND, 0, side.addr};
+  } else if constexpr (std::is_same_v<T, Move::Pc>) {
+    return {Unit::UNIT_PC};
+  } else if constexpr (std::is_same_v<T, Move::PcIf>) {
+    return {Unit::UNIT_PC_IF, side.alu};
+  } else if constexpr (std::is_same_v<T, Move::AbsImmediate>) {
+    return {Unit::UNIT_ABS_IMMEDIATE, side.value};
+  } else {
+    static_assert(std::is_same_v<T, Move::AbsOperand>);
+    return {Unit::UNIT_ABS_OPERAND, 0, side.value};
+  }
+}
+
+}  // namespace
+
+std::optional<Move> Move::FromInstr(const Instr& instr) {
+  if (!IsValidSource(instr.src_unit()) || !IsValidDest(instr.dst_unit()) ||
+      (instr.UsesSoperand() && !instr.soperand()) ||
+      (instr.UsesDoperand() && !instr.doperand())) {
+    return std::nullopt;
+  }
+
+  Move move;
+  unsigned short si = instr.si();
+  switch (instr.src_unit()) {
+    case Unit::UNIT_REGISTER:
+      move.src = Register{si};
+      break;
+    case Unit::UNIT_REGISTER_POINTER:
+      move.src = RegisterPointer{si};
+      break;
+    case Unit::UNIT_ALU_LEFT:
+    case Unit::UNIT_ALU_RIGHT:
+    case Unit::UNIT_ALU_OPERATOR:
+      move.src = AluInput{si, PortFor(instr.src_unit())};
+      break;
+    case Unit::UNIT_ALU_RESULT:
+      move.src = AluResult{si};
+      break;
+    case Unit::UNIT_MEMORY_IMMEDIATE:
+      move.src = MemoryImmediate{si};
+      break;
+    case Unit::UNIT_MEMORY_OPERAND:
+      move.src = MemoryOperand{*instr.soperand()};
+      break;
+    case Unit::UNIT_PC:
+      move.src = Pc{};
+      break;
+    case Unit::UNIT_ABS_IMMEDIATE:
+      move.src = AbsImmediate{si};
+      break;
+    case Unit::UNIT_ABS_OPERAND:
+      move.src = AbsOperand{*instr.soperand()};
+      break;
+    default:
+      move.src = Nothing{};
+      break;
+  }
+
+  unsigned short di = instr.di();
+  switch (instr.dst_unit()) {
+    case Unit::UNIT_REGISTER:
+      move.dst = Register{di};
+      break;
+    case Unit::UNIT_REGISTER_POINTER:
+      move.dst = RegisterPointer{di};
+      break;
+    case Unit::UNIT_ALU_LEFT:
+    case Unit::UNIT_ALU_RIGHT:
+    case Unit::UNIT_ALU_OPERATOR:
+      move.dst = AluInput{di, PortFor(instr.dst_unit())};
+      break;
+    case Unit::UNIT_MEMORY_IMMEDIATE:
+      move.dst = MemoryImmediate{di};
+      break;
+    case Unit::UNIT_MEMORY_OPERAND:
+      move.dst = MemoryOperand{*instr.doperand()};
+      break;
+    case Unit::UNIT_PC:
+      move.dst = Pc{};
+      break;
+    case Unit::UNIT_PC_IF:
+      move.dst = PcIf{di};
+      break;
+    default:
+      move.dst = Nothing{};
+      break;
+  }
+  // Units without an index drop their immediate; refuse rather than lose
+  // it.
+  if (move.ToInstr() != instr)
+    return std::nullopt;
+  return move;
+}
+
+Instr Move::ToInstr() const {
+  Encoded s = std::visit([](const auto& side) { return Encode(side); }, src);
+  Encoded d = std::visit([](const auto& side) { return Encode(side); }, dst);
+  Instr instr;
+  instr.Src(s.unit).Si(s.immediate).Dst(d.unit).Di(d.immediate);
+  if (s.operand)
+    instr.Soperand(*s.operand);
+  if (d.operand)
+    instr.Doperand(*d.operand);
+  return instr;
+}
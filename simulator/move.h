@@ -0,0 +1,77 @@
+#pragma once
+
+#include <cstdint>
+#include <optional>
+#include <variant>
+
+#include "assembler.h"
+
+// A move as data rather than a builder: each side is one of the things the
+// core can actually read from or write to, carrying only the fields that
+// apply. A source that can't be written, such as an absolute value, has no
+// destination alternative, so a Move is always a legal direction with its
+// operands present. Convert from and to Instr for assembly.
+struct Move {
+  struct Nothing {};
+  struct Register {
+    unsigned short index;
+  };
+  // The data memory word whose address register `reg` holds.
+  struct RegisterPointer {
+    unsigned short reg;
+  };
+  struct AluInput {
+    enum class Port { LEFT, RIGHT, OPERATOR };
+    unsigned short alu;
+    Port port;
+  };
+  struct AluResult {
+    unsigned short alu;
+  };
+  struct MemoryImmediate {
+    unsigned short addr;
+  };
+  struct MemoryOperand {
+    uint32_t addr;
+  };
+  struct Pc {};
+  // Jump if ALU `alu`'s result is non-zero.
+  struct PcIf {
+    unsigned short alu;
+  };
+  struct AbsImmediate {
+    unsigned short value;
+  };
+  struct AbsOperand {
+    uint32_t value;
+  };
+
+  using Source = std::variant<Nothing,
+                              Register,
+                              RegisterPointer,
+                              AluInput,
+                              AluResult,
+                              MemoryImmediate,
+                              MemoryOperand,
+                              Pc,
+                              AbsImmediate,
+                              AbsOperand>;
+  using Dest = std::variant<Nothing,
+                            Register,
+                            RegisterPointer,
+                            AluInput,
+                            MemoryImmediate,
+                            MemoryOperand,
+                            Pc,
+                            PcIf>;
+
+  Source src;
+  Dest dst;
+
+  // Nothing if `instr` uses a unit in a direction the core doesn't
+  // support (see IsValidSource/IsValidDest), is missing an operand, or
+  // sets an immediate its unit ignores, such as UNIT_PC's. Otherwise
+  // ToInstr() gives back an equal Instr.
+  static std::optional<Move> FromInstr(const Instr& instr);
+  Instr ToInstr() const;
+};
@@ -0,0 +1,89 @@
+#include "optimizer.h"
+
+#include <vector>
+
+namespace {
+
+// Units whose value is held by the core and written by a plain move, so a
+// write to them can be dropped if nothing reads it.
+bool IsStorage(Unit u) {
+  switch (u) {
+    case Unit::UNIT_REGISTER:
+    case Unit::UNIT_ALU_LEFT:
+    case Unit::UNIT_ALU_RIGHT:
+    case Unit::UNIT_ALU_OPERATOR:
+      return true;
+    default:
+      return false;
+  }
+}
+
+bool IsPureSource(Unit u) {
+  switch (u) {
+    case Unit::UNIT_NONE:
+    case Unit::UNIT_REGISTER:
+    case Unit::UNIT_ALU_LEFT:
+    case Unit::UNIT_ALU_RIGHT:
+    case Unit::UNIT_ALU_OPERATOR:
+    case Unit::UNIT_ABS_IMMEDIATE:
+    case Unit::UNIT_ABS_OPERAND:
+      return true;
+    default:
+      return false;
+  }
+}
+
+bool TouchesPC(const Instr& instr) {
+  return instr.src_unit() == Unit::UNIT_PC ||
+         instr.dst_unit() == Unit::UNIT_PC ||
+         instr.dst_unit() == Unit::UNIT_PC_IF;
+}
+
+bool IsSelfMove(const Instr& instr) {
+  Unit u = instr.src_unit();
+  return u == instr.dst_unit() && instr.si() == instr.di() &&
+         (u == Unit::UNIT_REGISTER || u == Unit::UNIT_ALU_LEFT ||
+          u == Unit::UNIT_ALU_RIGHT);
+}
+
+// Whether `instr`'s source reads what was stored by a move to `dst` with
+// immediate `di`.
+bool Reads(const Instr& instr, Unit dst, unsigned short di) {
+  Unit src = instr.src_unit();
+  if (instr.si() != di)
+    return false;
+  if (dst == Unit::UNIT_REGISTER)
+    return src == Unit::UNIT_REGISTER || src == Unit::UNIT_REGISTER_POINTER;
+  // An ALU's result depends on its inputs and operator.
+  return src == dst || src == Unit::UNIT_ALU_RESULT;
+}
+
+// Whether `next` makes `prev` dead.
+bool Overwrites(const Instr& next, const Instr& prev) {
+  return IsStorage(prev.dst_unit()) && IsPureSource(prev.src_unit()) &&
+         next.dst_unit() == prev.dst_unit() && next.di() == prev.di() &&
+         !Reads(next, prev.dst_unit(), prev.di());
+}
+
+}  // namespace
+
+Program Optimize(const Program& program) {
+  for (const Instr& instr : program) {
+    if (TouchesPC(instr))
+      return program;
+  }
+
+  std::vector<Instr> out;
+  for (const Instr& instr : program) {
+    if (IsSelfMove(instr))
+      continue;
+    while (!out.empty() && Overwrites(instr, out.back()))
+      out.pop_back();
+    out.push_back(instr);
+  }
+
+  Program optimized;
+  for (const Instr& instr : out)
+    optimized.Add(instr);
+  return optimized;
+}
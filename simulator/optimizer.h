@@ -0,0 +1,17 @@
+#pragma once
+
+#include "assembler.h"
+
+// Peephole optimizations over a straight-line Program:
+//
+//   * Moves from a register or ALU input to itself are dropped.
+//   * A move into a register, ALU input or ALU operator that the very next
+//     move overwrites without reading is dropped. This also collapses
+//     repeated identical moves, such as setting the same ALU operator
+//     twice.
+//
+// Only moves whose source has no side effects are ever dropped: memory
+// reads may hit a device, and reading UNIT_ALU_RESULT latches the result
+// UNIT_PC_IF tests. Programs that touch the PC are returned unchanged,
+// since dropping moves would shift any hard-coded jump targets.
+Program Optimize(const Program& program);
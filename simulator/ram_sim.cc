@@ -1,5 +1,7 @@
 #include "ram_sim.h"
 
+#include <algorithm>
+
 RAMSim::RAMSim(size_t size,
                CData& wstrb_o,
                CData& valid_o,
@@ -15,32 +17,71 @@ RAMSim::RAMSim(size_t size,
       write_data_(write_data),
       addr_o_(addr_o) {
   mem_.resize(size);
+  if (size)
+    regions_.push_back({kFlatRegion, 0, (uint32_t)size, nullptr});
+}
+
+void RAMSim::AddRegion(const std::string& name,
+                       uint32_t base,
+                       uint32_t len,
+                       MemoryBackend* backend) {
+  regions_.push_back({name, base, len, backend});
+}
+
+void RAMSim::RemoveRegion(const std::string& name) {
+  regions_.erase(std::remove_if(regions_.begin(), regions_.end(),
+                                [&name](const Region& region) {
+                                  return region.name == name;
+                                }),
+                 regions_.end());
+}
+
+RAMSim::Region* RAMSim::FindRegion(uint32_t addr) {
+  for (auto it = regions_.rbegin(); it != regions_.rend(); ++it) {
+    if (addr >= it->base && addr - it->base < it->len)
+      return &*it;
+  }
+  return nullptr;
 }
 
 void RAMSim::Do() {
+  if (hung_) {
+    *ready_i_ = 0;
+    return;
+  }
   if (valid_o_) {
-    IData* data = &mem_[addr_o_];
-    if (wstrb_o_ != 0) {
-      CData* cd = (CData*)data;
-      CData* wd = (CData*)&write_data_;
-      if (wstrb_o_ & 0x01) {
-        cd[0] = wd[0];
-      }
-      if (wstrb_o_ & 0x02) {
-        cd[1] = wd[1];
-      }
-      if (wstrb_o_ & 0x04) {
-        cd[2] = wd[2];
-      }
-      if (wstrb_o_ & 0x08) {
-        cd[3] = wd[3];
-      }
+    Region* region = FindRegion(addr_o_);
+    if (region && region->backend) {
+      uint32_t offset = addr_o_ - region->base;
+      // Writes hold valid until the next move, so only pass on the first
+      // cycle of each; devices may count or queue what they're sent.
+      if (wstrb_o_ != 0 && !*ready_i_)
+        region->backend->Write(offset, write_data_, wstrb_o_);
+      *read_data_ = region->backend->Read(offset);
+    } else if (region) {
+      IData* data = &mem_[addr_o_];
+      if (wstrb_o_ != 0)
+        *data = MergeBytes(*data, write_data_, wstrb_o_);
+      *read_data_ = *data;
+    } else {
+      // Valid is held for as long as the master likes; only report the
+      // first cycle of each access.
+      if (!*ready_i_)
+        unmapped_.push_back(addr_o_);
+      *read_data_ = fault_value_;
     }
-    *read_data_ = *data;
   }
   *ready_i_ = valid_o_;
 }
 
+void RAMSim::Clear() {
+  std::fill(mem_.begin(), mem_.end(), fill_value_);
+  *ready_i_ = 0;
+  *read_data_ = 0;
+  unmapped_.clear();
+  hung_ = false;
+}
+
 void RAMSim::Randomize() {
   for (int i = 0; i < size_; i++) {
     mem_[i] = rand() % 255;
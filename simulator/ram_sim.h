@@ -6,7 +6,13 @@
 #include <cstdint>
 #include <cstdlib>
 #include <random>
+#include <string>
+#include <vector>
 
+#include "memory_backend.h"
+
+// Word-addressed memory behind one of the core's buses: addr_o indexes
+// 32-bit words, and wstrb_o selects the bytes of a write.
 class RAMSim {
  public:
   explicit RAMSim(size_t size,
@@ -20,9 +26,42 @@ class RAMSim {
   // Fill memory with garbage to simulate what real memory often looks like.
   void Randomize();
 
+  // Reset the memory contents to the fill value and drop any pending
+  // ready/read data.
+  void Clear();
+
+  // What Clear() fills memory with; 0 unless set.
+  void SetFillValue(IData value) { fill_value_ = value; }
+  IData fill_value() const { return fill_value_; }
+
+  // The flat memory from mem() is mapped at [0, size) as a region of this
+  // name. Remove it to leave only the regions added by hand mapped.
+  static constexpr const char* kFlatRegion = "mem";
+
+  // Route accesses to [base, base + len) to `backend`, which must outlive
+  // this RAMSim. Where regions overlap, the one added last wins, so
+  // devices can be mapped over the flat memory.
+  void AddRegion(const std::string& name,
+                 uint32_t base,
+                 uint32_t len,
+                 MemoryBackend* backend);
+  // Drop every region added under `name`.
+  void RemoveRegion(const std::string& name);
+
+  // Value read back from addresses outside every region.
+  void SetFaultValue(IData value) { fault_value_ = value; }
+
+  // Addresses of every access that hit no region, in order.
+  const std::vector<IData>& unmapped() const { return unmapped_; }
+
+  // Stop answering: ready stays low and writes are dropped until Clear(),
+  // like a device that has locked up.
+  void Hang() { hung_ = true; }
+
   void Do();
 
   std::vector<IData>& mem() { return mem_; }
+  const std::vector<IData>& mem() const { return mem_; }
 
  private:
   CData &wstrb_o_, &valid_o_;
@@ -31,6 +70,20 @@ class RAMSim {
   IData& write_data_;
   IData& addr_o_;
 
+  struct Region {
+    std::string name;
+    uint32_t base;
+    uint32_t len;
+    // Null for the flat memory.
+    MemoryBackend* backend;
+  };
+  Region* FindRegion(uint32_t addr);
+
   const size_t size_;
   std::vector<IData> mem_;
+  std::vector<Region> regions_;
+  IData fault_value_ = 0;
+  IData fill_value_ = 0;
+  std::vector<IData> unmapped_;
+  bool hung_ = false;
 };
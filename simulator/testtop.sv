@@ -1,6 +1,7 @@
 module testtop(
     input wire rst_i,
     input wire sysclk_i,
+    input wire [31:0] reset_vector_i,
 
     input logic [31:0] instr_data_read_i,
     output logic [31:0] instr_data_write_o,
@@ -17,7 +18,11 @@ module testtop(
     input logic data_ready_i,
 
     output logic [31:0] cycles_executed_o,
-    output wire instr_done_o
+    output wire instr_done_o,
+    output wire [31:0] pc_o,
+    output wire [31:0] move_value_o,
+    // Register N in bits [N*32 +: 32].
+    output wire [32*32-1:0] registers_o
 );
 
     always @(posedge sysclk_i) begin
@@ -49,9 +54,13 @@ module testtop(
     tta tta(
         .rst_i(rst_i),
         .clk_i(sysclk_i),
+        .reset_vector_i(reset_vector_i),
         .instr_bus(instr_bus),
         .data_bus(data_bus),
-        .instr_done_o(instr_done_o)
+        .instr_done_o(instr_done_o),
+        .pc_o(pc_o),
+        .move_value_o(move_value_o),
+        .registers_o(registers_o)
     );
 
 endmodule : testtop
\ No newline at end of file
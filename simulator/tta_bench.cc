@@ -0,0 +1,128 @@
+#include <glog/logging.h>
+
+#include <chrono>
+#include <iostream>
+
+#include "assembler.h"
+#include "tta_harness.h"
+
+// Compares the cost of stepping the model one cycle at a time against the
+// batched run path, over a program that keeps both buses busy, and reports
+// the model's throughput in moves per second. Also times running many
+// short programs on a fresh model each against one model reset between
+// them, and assembling a large program into one buffer against assembling
+// each instruction separately.
+
+namespace {
+
+constexpr int kCycles = 10000;
+constexpr int kAssembleInstrs = 10000;
+constexpr int kAssembleRounds = 100;
+constexpr int kThroughputMoves = 1000;
+constexpr int kShortPrograms = 100;
+
+Program BusyProgram() {
+  Program program;
+  // Half the instruction memory; the zero words after it run as no-ops.
+  for (int i = 0; i < 512; i++) {
+    program.Mov(Unit::UNIT_ABS_IMMEDIATE, i, Unit::UNIT_MEMORY_IMMEDIATE, i);
+  }
+  return program;
+}
+
+template <typename F>
+double TimeMillis(F f) {
+  auto start = std::chrono::steady_clock::now();
+  f();
+  std::chrono::duration<double, std::milli> elapsed =
+      std::chrono::steady_clock::now() - start;
+  return elapsed.count();
+}
+
+}  // namespace
+
+int main(int argc, char** argv) {
+  FLAGS_logtostderr = true;
+  google::InitGoogleLogging(argv[0]);
+
+  TTAHarness harness;
+  Program program = BusyProgram();
+
+  harness.Reset();
+  harness.Load(program);
+  double stepped = TimeMillis([&] { harness.RunUntil(kCycles); });
+
+  harness.ResetModel();
+  harness.Load(program);
+  double batched = TimeMillis([&] { harness.RunBatch(kCycles); });
+
+  std::cout << "RunUntil: " << kCycles << " cycles in " << stepped << "ms"
+            << std::endl;
+  std::cout << "RunBatch: " << kCycles << " cycles in " << batched << "ms"
+            << std::endl;
+
+  // What a property test does per case: one small program from reset.
+  Program short_program =
+      Program()
+          .Mov(Unit::UNIT_ABS_IMMEDIATE, 7, Unit::UNIT_REGISTER, 0)
+          .Mov(Unit::UNIT_REGISTER, 0, Unit::UNIT_MEMORY_IMMEDIATE, 100);
+  double fresh = TimeMillis([&] {
+    for (int i = 0; i < kShortPrograms; i++) {
+      TTAHarness fresh_harness;
+      CHECK(fresh_harness.RunProgram(short_program, 100));
+    }
+  });
+  double reused = TimeMillis([&] {
+    for (int i = 0; i < kShortPrograms; i++)
+      CHECK(harness.RunProgram(short_program, 100));
+  });
+  std::cout << "New model per program: " << kShortPrograms << " programs in "
+            << fresh << "ms" << std::endl;
+  std::cout << "ResetModel() between programs: " << kShortPrograms
+            << " programs in " << reused << "ms" << std::endl;
+
+  // Register-to-register moves, so the count isn't dominated by memory.
+  Program moves;
+  for (int i = 0; i < kThroughputMoves; i++)
+    moves.Mov(Unit::UNIT_REGISTER, i % 32, Unit::UNIT_REGISTER, (i + 1) % 32);
+  std::optional<int> cycles;
+  double run = TimeMillis(
+      [&] { cycles = harness.RunProgram(moves, 20 * kThroughputMoves); });
+  CHECK(cycles) << "throughput program didn't finish";
+  std::cout << "RunProgram: " << kThroughputMoves << " moves in " << *cycles
+            << " cycles, " << run << "ms ("
+            << (int)(harness.instructions_retired() / (run / 1000))
+            << " moves/s)" << std::endl;
+
+  Program big;
+  for (int i = 0; i < kAssembleInstrs; i++) {
+    big.Add(Instr()
+                .Src(Unit::UNIT_ABS_OPERAND)
+                .Soperand(i)
+                .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+                .Di(i % 1024));
+  }
+  size_t words = 0;
+  double per_instr = TimeMillis([&] {
+    for (int round = 0; round < kAssembleRounds; round++) {
+      std::vector<uint32_t> prg;
+      for (const Instr& instr : big) {
+        for (uint32_t op : instr.assemble())
+          prg.push_back(op);
+      }
+      words += prg.size();
+    }
+  });
+  double into = TimeMillis([&] {
+    for (int round = 0; round < kAssembleRounds; round++)
+      words += big.assemble().size();
+  });
+  std::cout << "Instr::assemble() per instruction: " << kAssembleRounds
+            << " x " << kAssembleInstrs << " instructions in " << per_instr
+            << "ms" << std::endl;
+  std::cout << "Program::assemble(): " << kAssembleRounds << " x "
+            << kAssembleInstrs << " instructions in " << into << "ms"
+            << std::endl;
+  LOG(INFO) << words << " words assembled";
+  return 0;
+}
@@ -0,0 +1,514 @@
+#include "tta_harness.h"
+
+#include <algorithm>
+#include <iomanip>
+#include <iterator>
+#include <sstream>
+
+namespace {
+
+// Column names of a golden port trace; inputs first. SamplePorts() must
+// match.
+constexpr const char* kPortNames[] = {
+    "rst_i",          "sysclk_i",          "instr_data_read_i",
+    "instr_ready_i",  "data_data_read_i",  "data_ready_i",
+    "instr_addr_o",   "instr_valid_o",     "instr_instr_o",
+    "instr_data_write_o", "data_addr_o",   "data_valid_o",
+    "data_wstrb_o",   "data_data_write_o", "instr_done_o",
+    "pc_o"};
+constexpr size_t kNumInputPorts = 6;
+
+}  // namespace
+
+TTAHarness::TTAHarness(const std::string& trace_file)
+    : top_(std::make_unique<Vtesttop>()),
+      clock_gen_(1, 1 /* reset_cycles */, &top_->rst_i, &top_->sysclk_i),
+      prg_(1024,
+           c_gnd_,
+           top_->instr_valid_o,
+           &top_->instr_ready_i,
+           &top_->instr_data_read_i,
+           i_gnd_,
+           top_->instr_addr_o),
+      ram_(1024,
+           top_->data_wstrb_o,
+           top_->data_valid_o,
+           &top_->data_ready_i,
+           &top_->data_data_read_i,
+           top_->data_data_write_o,
+           top_->data_addr_o) {
+  if (!trace_file.empty())
+    OpenTrace(trace_file);
+}
+
+TTAHarness::TTAHarness(
+    const std::vector<std::pair<uint32_t, uint32_t>>& data,
+    const std::string& trace_file)
+    : TTAHarness(trace_file) {
+  data_seed_ = data;
+  LoadData(data_seed_);
+}
+
+TTAHarness::~TTAHarness() {
+  CloseTrace();
+}
+
+void TTAHarness::OpenTrace(const std::string& filename) {
+  Verilated::traceEverOn(true);
+  trace_ = std::make_unique<VerilatedFstC>();
+  top_->trace(trace_.get(), 99);
+  trace_->open(filename.c_str());
+}
+
+void TTAHarness::CloseTrace() {
+  if (!trace_)
+    return;
+  trace_->flush();
+  trace_->close();
+  trace_.reset();
+}
+
+std::string FormatWord(uint32_t w) {
+  std::ostringstream os;
+  os << "0x" << std::hex << std::setw(8) << std::setfill('0') << w
+     << std::dec << " (" << w << ", " << (int32_t)w << ")";
+  return os.str();
+}
+
+std::ostream& operator<<(std::ostream& os, const Coverage& coverage) {
+  os << "units:";
+  for (Unit u : coverage.units_used)
+    os << " " << u;
+  os << "; ALU ops:";
+  for (ALUOp op : coverage.alu_ops_used)
+    os << " " << op;
+  return os;
+}
+
+std::string ToJson(const MachineState& state) {
+  std::ostringstream os;
+  os << "{\n  \"pc\": " << state.pc << ",\n  \"registers\": [";
+  const char* sep = "";
+  for (uint32_t value : state.registers) {
+    os << sep << value;
+    sep = ", ";
+  }
+  os << "],\n  \"data_mem\": {";
+  sep = "\n";
+  for (const auto& [addr, value] : state.data_mem) {
+    os << sep << "    \"" << addr << "\": " << value;
+    sep = ",\n";
+  }
+  os << (state.data_mem.empty() ? "}" : "\n  }") << "\n}\n";
+  return os.str();
+}
+
+void TTAHarness::ResetModel() {
+  clock_gen_.Reset();
+  data_valid_ = 0;
+  data_wstrb_ = 0;
+  data_addr_ = 0;
+  pending_read_.reset();
+  fetching_opcode_ = false;
+  fetch_addr_ = 0;
+  in_flight_addr_ = 0;
+  mem_trace_.clear();
+  move_trace_.clear();
+  last_done_ = 0;
+  instructions_retired_ = 0;
+  last_done_cycle_.reset();
+  in_flight_dst_.reset();
+  cycle_histogram_.clear();
+  instr_monitor_.Clear();
+  data_monitor_.Clear();
+  ClearInstructions();
+  ClearData();
+  LoadData(data_seed_);
+}
+
+void TTAHarness::ClearInstructions() {
+  ClearInstructionFetchHook();
+  prg_.Clear();
+}
+
+StepResult TTAHarness::Step() {
+  StepResult result;
+  clock_gen_.Step(trace_.get());
+  top_->eval();
+  if (!top_->rst_i & clock_gen_.Bus()) {
+    bool data_start = top_->data_valid_o &&
+                      (!data_valid_ || top_->data_addr_o != data_addr_ ||
+                       top_->data_wstrb_o != data_wstrb_);
+    BusSnapshot bus = PeekBus();
+    instr_monitor_.Observe(clock_gen_.cycles(),
+                           {bus.instr_valid, bus.instr_ready, bus.instr_addr,
+                            top_->instr_data_write_o, 0});
+    data_monitor_.Observe(clock_gen_.cycles(),
+                          {bus.data_valid, bus.data_ready, bus.data_addr,
+                           bus.data_write, bus.data_wstrb});
+    ram_.Do();
+    prg_.Do();
+
+    if (data_start) {
+      if (top_->data_wstrb_o) {
+        uint32_t value = top_->data_data_write_o;
+        result.data_write = {top_->data_addr_o, value};
+        if (mem_trace_enabled_) {
+          mem_trace_.push_back(
+              {clock_gen_.cycles(), top_->data_addr_o, value, true});
+        }
+      } else {
+        pending_read_ = MemAccess{clock_gen_.cycles(), top_->data_addr_o, 0,
+                                  false};
+      }
+    }
+    // A read's data is only good once ready rises, which may be a cycle or
+    // more after it starts, or never if memory hangs.
+    if (pending_read_ && top_->data_ready_i) {
+      pending_read_->value = top_->data_data_read_i;
+      result.data_read = {pending_read_->addr, pending_read_->value};
+      if (mem_trace_enabled_)
+        mem_trace_.push_back(*pending_read_);
+      pending_read_.reset();
+    }
+    data_valid_ = top_->data_valid_o;
+    data_wstrb_ = top_->data_wstrb_o;
+    data_addr_ = top_->data_addr_o;
+
+    // The sequencer holds valid high between fetches, so a new opcode
+    // fetch shows up as instr going high or the address moving.
+    bool fetching_opcode = top_->instr_valid_o && top_->instr_instr_o;
+    if (fetching_opcode &&
+        (!fetching_opcode_ || top_->instr_addr_o != fetch_addr_)) {
+      OnFetch(top_->instr_data_read_i);
+      in_flight_addr_ = top_->instr_addr_o;
+      result.instr_fetch = top_->instr_addr_o;
+    }
+    fetching_opcode_ = fetching_opcode;
+    fetch_addr_ = top_->instr_addr_o;
+    if (in_flight_dst_)
+      cycle_histogram_[*in_flight_dst_]++;
+  }
+  result.instr_done = top_->instr_done_o;
+  if (move_trace_enabled_ && top_->instr_done_o && !last_done_ &&
+      in_flight_dst_) {
+    std::vector<uint32_t> words = InstrWordsAt(in_flight_addr_);
+    Instr instr;
+    size_t len;
+    DecodeError error;
+    if (!words.empty() && Instr::TryDecode(words, 0, &instr, &len, &error))
+      move_trace_.push_back({instr, top_->move_value_o});
+  }
+  CountRetired();
+  if (port_trace_) {
+    const char* sep = "";
+    for (uint32_t value : SamplePorts()) {
+      *port_trace_ << sep << std::hex << value;
+      sep = " ";
+    }
+    *port_trace_ << std::dec << "\n";
+  }
+  return result;
+}
+
+std::vector<uint32_t> TTAHarness::InstrWordsAt(uint32_t addr) const {
+  std::vector<uint32_t> words;
+  for (uint32_t a = addr; a < addr + 3; a++) {
+    if (fetch_hook_backend_)
+      words.push_back(fetch_hook_(a));
+    else if (a < prg_.mem().size())
+      words.push_back(prg_.mem()[a]);
+  }
+  return words;
+}
+
+void TTAHarness::CountRetired() {
+  if (top_->instr_done_o && !last_done_) {
+    instructions_retired_++;
+    last_done_cycle_ = clock_gen_.cycles();
+  }
+  last_done_ = top_->instr_done_o;
+}
+
+void TTAHarness::OnFetch(uint32_t op) {
+  Unit src = (Unit)((op >> kSrcUnitShift) & kSrcUnitMask);
+  uint32_t si = (op >> kSiShift) & kSiMask;
+  Unit dst = (Unit)((op >> kDstUnitShift) & kDstUnitMask);
+  coverage_.units_used.insert(src);
+  coverage_.units_used.insert(dst);
+  in_flight_dst_ = dst;
+  if (src == Unit::UNIT_ABS_IMMEDIATE && dst == Unit::UNIT_ALU_OPERATOR)
+    coverage_.alu_ops_used.insert((ALUOp)si);
+}
+
+int TTAHarness::RunUntil(int max_clocks) {
+  int start_clk = clock_gen_.cycles();
+  while (!Verilated::gotFinish() &&
+         (clock_gen_.cycles() < max_clocks + start_clk)) {
+    Step();
+  }
+  return clock_gen_.cycles() - start_clk;
+}
+
+int TTAHarness::RunBatch(int cycles) {
+  int start_clk = clock_gen_.cycles();
+  while (!Verilated::gotFinish() &&
+         (clock_gen_.cycles() < cycles + start_clk)) {
+    clock_gen_.Step(trace_.get());
+    top_->eval();
+    if (!top_->rst_i & clock_gen_.Bus()) {
+      // An idle bus with ready already low has nothing for RAMSim to do.
+      if (top_->data_valid_o || top_->data_ready_i)
+        ram_.Do();
+      if (top_->instr_valid_o || top_->instr_ready_i)
+        prg_.Do();
+      // Keep the edge detection Step() relies on current, so a Step()
+      // after the batch doesn't see an access or fetch already underway
+      // as a new one.
+      data_valid_ = top_->data_valid_o;
+      data_wstrb_ = top_->data_wstrb_o;
+      data_addr_ = top_->data_addr_o;
+      fetching_opcode_ = top_->instr_valid_o && top_->instr_instr_o;
+      fetch_addr_ = top_->instr_addr_o;
+    }
+    CountRetired();
+  }
+  // The batch didn't decode fetches or watch reads, so whatever Step()
+  // last saw in flight is long gone.
+  in_flight_dst_.reset();
+  pending_read_.reset();
+  return clock_gen_.cycles() - start_clk;
+}
+
+std::optional<Stall> TTAHarness::RunWithWatchdog(int max_cycles,
+                                                 int stall_cycles) {
+  int start_clk = clock_gen_.cycles();
+  int idle_cycles = 0;
+  IData last_pc = top_->instr_addr_o;
+  CData last_done = top_->instr_done_o;
+  while (!Verilated::gotFinish() &&
+         (clock_gen_.cycles() < max_cycles + start_clk)) {
+    Step();
+    if (!clock_gen_.Bus() || top_->rst_i)
+      continue;
+
+    bool done_rose = top_->instr_done_o && !last_done;
+    if (done_rose || top_->instr_addr_o != last_pc)
+      idle_cycles = 0;
+    else if (++idle_cycles >= stall_cycles)
+      return Stall{top_->instr_addr_o, clock_gen_.cycles()};
+    last_pc = top_->instr_addr_o;
+    last_done = top_->instr_done_o;
+  }
+  return std::nullopt;
+}
+
+BusSnapshot TTAHarness::PeekBus() const {
+  return {top_->instr_addr_o,
+          (bool)top_->instr_valid_o,
+          (bool)top_->instr_ready_i,
+          top_->data_addr_o,
+          (bool)top_->data_valid_o,
+          (bool)top_->data_ready_i,
+          top_->data_wstrb_o,
+          top_->data_data_write_o,
+          top_->data_data_read_i};
+}
+
+std::vector<uint32_t> TTAHarness::SamplePorts() const {
+  return {top_->rst_i,          top_->sysclk_i,          top_->instr_data_read_i,
+          top_->instr_ready_i,  top_->data_data_read_i,  top_->data_ready_i,
+          top_->instr_addr_o,   top_->instr_valid_o,     top_->instr_instr_o,
+          top_->instr_data_write_o, top_->data_addr_o,   top_->data_valid_o,
+          top_->data_wstrb_o,   top_->data_data_write_o, top_->instr_done_o,
+          top_->pc_o};
+}
+
+void TTAHarness::StartPortRecording(const std::string& path) {
+  port_trace_ = std::make_unique<std::ofstream>(path);
+  CHECK(*port_trace_) << "Can't write " << path;
+  const char* sep = "";
+  for (const char* name : kPortNames) {
+    *port_trace_ << sep << name;
+    sep = " ";
+  }
+  *port_trace_ << "\n";
+}
+
+void TTAHarness::StopPortRecording() {
+  port_trace_.reset();
+}
+
+std::optional<PortMismatch> TTAHarness::ReplayAndCompare(
+    const std::string& path) {
+  std::ifstream in(path);
+  CHECK(in) << "Can't read " << path;
+  std::string header;
+  std::getline(in, header);
+  std::istringstream names(header);
+  CHECK(std::equal(std::begin(kPortNames), std::end(kPortNames),
+                   std::istream_iterator<std::string>(names),
+                   std::istream_iterator<std::string>()))
+      << path << " doesn't have the expected ports: " << header;
+
+  std::string line;
+  for (int step = 0; std::getline(in, line); step++) {
+    Step();
+    std::vector<uint32_t> actual = SamplePorts();
+    std::istringstream fields(line);
+    for (size_t i = 0; i < actual.size(); i++) {
+      uint32_t expected;
+      CHECK(fields >> std::hex >> expected) << path << ": short line " << line;
+      if (i >= kNumInputPorts && expected != actual[i])
+        return PortMismatch{step, kPortNames[i], expected, actual[i]};
+    }
+  }
+  return std::nullopt;
+}
+
+std::optional<int> TTAHarness::RunProgram(const Program& program,
+                                          int max_cycles) {
+  ResetModel();
+  Load(program);
+  const uint32_t end = program.LenWords();
+  while (!Verilated::gotFinish() && top_->rst_i)
+    Step();
+  int start_clk = clock_gen_.cycles();
+  while (!Verilated::gotFinish() &&
+         (clock_gen_.cycles() < max_cycles + start_clk)) {
+    if (Step().instr_fetch == end)
+      return clock_gen_.cycles() - start_clk;
+  }
+  return std::nullopt;
+}
+
+void TTAHarness::SetUninitializedFill(uint32_t value) {
+  ram_.SetFillValue(value);
+  ClearData();
+  LoadData(data_seed_);
+}
+
+void TTAHarness::SetInstructionFetchHook(
+    std::function<uint32_t(uint32_t)> hook) {
+  fetch_hook_ = std::move(hook);
+  if (fetch_hook_backend_)
+    return;
+  fetch_hook_backend_ = std::make_unique<FunctionBackend>(
+      [this](uint32_t addr) { return fetch_hook_(addr); });
+  prg_.AddRegion("fetch_hook", 0, UINT32_MAX, fetch_hook_backend_.get());
+}
+
+void TTAHarness::ClearInstructionFetchHook() {
+  prg_.RemoveRegion("fetch_hook");
+  fetch_hook_backend_.reset();
+  fetch_hook_ = nullptr;
+}
+
+MachineState TTAHarness::DumpState() {
+  MachineState state{top_->pc_o, {}, {}};
+  for (int r = 0; r < kNumRegisters; r++)
+    state.registers[r] = top_->registers_o[r];
+  for (uint32_t addr = 0; addr < ram_.mem().size(); addr++) {
+    if (ram_.mem()[addr] != ram_.fill_value())
+      state.data_mem[addr] = ram_.mem()[addr];
+  }
+  return state;
+}
+
+std::string TTAHarness::DisassembleLoaded() {
+  const std::vector<IData>& mem = prg_.mem();
+  size_t end = mem.size();
+  while (end > 0 && mem[end - 1] == 0)
+    end--;
+  // Decode from all of memory, so an operand word of 0 just past `end`
+  // isn't lost.
+  std::vector<uint32_t> words(mem.begin(), mem.end());
+
+  std::ostringstream listing;
+  size_t pos = 0;
+  while (pos < end) {
+    Instr instr;
+    size_t len;
+    DecodeError error;
+    listing << std::hex << std::setw(4) << std::setfill('0') << pos
+            << std::dec << ": ";
+    if (!Instr::TryDecode(words, pos, &instr, &len, &error)) {
+      listing << error << "\n";
+      break;
+    }
+    listing << instr << "\n";
+    pos += len;
+  }
+  return listing.str();
+}
+
+std::vector<MemoryMismatch> TTAHarness::CompareData(
+    const std::vector<std::pair<uint32_t, uint32_t>>& expected) {
+  std::vector<MemoryMismatch> mismatches;
+  for (const auto& [addr, value] : expected) {
+    CHECK_LT(addr, ram_.mem().size());
+    if (ram_.mem()[addr] != value)
+      mismatches.push_back({addr, value, ram_.mem()[addr]});
+  }
+  return mismatches;
+}
+
+std::vector<MemAccess> TTAHarness::MemTraceFor(uint32_t addr) const {
+  std::vector<MemAccess> accesses;
+  for (const auto& access : mem_trace_) {
+    if (access.addr == addr)
+      accesses.push_back(access);
+  }
+  return accesses;
+}
+
+void TTAHarness::Load(const Program& program, uint32_t addr) {
+  Load(program.assemble(), addr);
+}
+
+bool TTAHarness::TryLoad(const Program& program,
+                         uint32_t addr,
+                         ProgramTooLarge* error) {
+  size_t capacity = addr < prg_.mem().size() ? prg_.mem().size() - addr : 0;
+  if (!program.FitsIn(capacity)) {
+    *error = {program.LenWords(), capacity};
+    return false;
+  }
+  Load(program, addr);
+  return true;
+}
+
+void TTAHarness::LoadLabeled(const LabeledProgram& program, uint32_t addr) {
+  Load(AssembleLabeled(program, addr), addr);
+}
+
+void TTAHarness::Load(const std::vector<uint32_t>& code, uint32_t addr) {
+  CHECK_LE(addr + code.size(), prg_.mem().size());
+  off_t pos = addr;
+  for (const auto& op : code) {
+    prg_.mem()[pos++] = op;
+  }
+}
+
+void TTAHarness::LoadData(
+    const std::vector<std::pair<uint32_t, uint32_t>>& entries) {
+  for (const auto& [addr, value] : entries) {
+    CHECK_LT(addr, ram_.mem().size());
+    ram_.mem()[addr] = value;
+  }
+}
+
+void TTAHarness::LoadDataMap(const std::map<uint32_t, uint32_t>& entries) {
+  LoadData({entries.begin(), entries.end()});
+}
+
+std::vector<std::pair<uint32_t, uint32_t>> TTAHarness::DumpDataRange(
+    uint32_t start,
+    uint32_t end) {
+  CHECK_LE(start, end);
+  CHECK_LE(end, ram_.mem().size());
+  std::vector<std::pair<uint32_t, uint32_t>> dump;
+  for (uint32_t addr = start; addr < end; addr++)
+    dump.emplace_back(addr, ram_.mem()[addr]);
+  return dump;
+}
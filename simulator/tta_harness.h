@@ -0,0 +1,412 @@
+#pragma once
+
+#include <glog/logging.h>
+#include <verilated_fst_c.h>
+
+#include <array>
+#include <cstdint>
+#include <fstream>
+#include <functional>
+#include <map>
+#include <memory>
+#include <optional>
+#include <ostream>
+#include <set>
+#include <string>
+#include <utility>
+#include <vector>
+
+#include "Vtesttop.h"
+#include "assembler.h"
+#include "bus_monitor.h"
+#include "clock_gen.h"
+#include "ram_sim.h"
+
+// Which units and ALU operators the instructions fetched so far used.
+// ALU operators are only known for moves of an immediate into
+// UNIT_ALU_OPERATOR.
+struct Coverage {
+  std::set<Unit> units_used;
+  std::set<ALUOp> alu_ops_used;
+};
+std::ostream& operator<<(std::ostream& os, const Coverage& coverage);
+
+// `w` as hex, unsigned and signed decimal, e.g. "0xffffffff (4294967295, -1)".
+std::string FormatWord(uint32_t w);
+
+// Bus activity seen during one Step(). Writes and fetches are reported on
+// the cycle they start, reads once their data is ready.
+struct StepResult {
+  bool instr_done = false;
+  // (address, value) of a data memory read or write.
+  std::optional<std::pair<uint32_t, uint32_t>> data_read;
+  std::optional<std::pair<uint32_t, uint32_t>> data_write;
+  // Address of an opcode fetch.
+  std::optional<uint32_t> instr_fetch;
+};
+
+// Both buses' signals as of one moment, read together by
+// TTAHarness::PeekBus().
+struct BusSnapshot {
+  uint32_t instr_addr;
+  bool instr_valid;
+  bool instr_ready;
+  uint32_t data_addr;
+  bool data_valid;
+  bool data_ready;
+  uint8_t data_wstrb;
+  uint32_t data_write;
+  uint32_t data_read;
+};
+
+// A data memory access recorded by TTAHarness's memory trace.
+struct MemAccess {
+  // Harness cycle the access started on.
+  int cycle;
+  uint32_t addr;
+  // Written, or read back once memory raised ready.
+  uint32_t value;
+  bool is_write;
+};
+
+// A move recorded by TTAHarness's move trace: the move as decoded from
+// instruction memory, or from the fetch hook if one is set, and the value
+// it carried from source to destination.
+struct MoveTrace {
+  Instr instr;
+  uint32_t value;
+};
+
+// Architectural state visible from outside the core, for comparing runs
+// against golden snapshots. ALUs aren't exposed by testtop.
+struct MachineState {
+  uint32_t pc;
+  // Register N at index N, from testtop's registers_o.
+  std::array<uint32_t, kNumRegisters> registers;
+  // Data memory words other than the uninitialized fill (normally 0), by
+  // address.
+  std::map<uint32_t, uint32_t> data_mem;
+};
+
+// `state` as JSON, with keys and addresses in a fixed order so the output
+// can be checked in and compared byte for byte.
+std::string ToJson(const MachineState& state);
+
+// A data memory word that didn't hold what was expected.
+struct MemoryMismatch {
+  uint32_t addr;
+  uint32_t expected;
+  uint32_t actual;
+};
+
+// Reported by TTAHarness::TryLoad() when a program doesn't fit.
+struct ProgramTooLarge {
+  // Words the program assembles to, and words free from the load address.
+  size_t needed;
+  size_t capacity;
+};
+
+// The first difference ReplayAndCompare() found from a golden port trace.
+struct PortMismatch {
+  // Steps since the replay started, counting from 0.
+  int step;
+  // testtop port name, e.g. "data_addr_o".
+  std::string port;
+  uint32_t expected;
+  uint32_t actual;
+};
+
+// Reported by RunWithWatchdog() when the core stops making progress.
+struct Stall {
+  // Instruction fetch address at the time of the stall.
+  uint32_t pc;
+  // Harness cycle at which the watchdog fired.
+  int cycle;
+};
+
+// Drives the verilated testtop: steps the clock, services the instruction
+// and data buses from simulated memories, and loads programs.
+//
+// Constructing the verilated model is by far the most expensive part of a
+// run, so a single harness can be reused across many programs. Call
+// ResetModel() between programs to re-drive the reset sequence and clear
+// both memories, then Load() the next program.
+//
+// Both buses are word addressed: instr_addr_o and data_addr_o count 32-bit
+// words, address n + 1 is the word after n, and every address in this
+// class (Load(), LoadData(), ram()->mem() indices, traces) is a word
+// address. Byte lanes within a word are picked by data_wstrb_o.
+//
+// While a trace is open every step is dumped to it, and it is flushed and
+// closed when the harness is destroyed.
+class TTAHarness {
+ public:
+  // If `trace_file` is non-empty, trace to it from the first step.
+  explicit TTAHarness(const std::string& trace_file = "");
+  // Also seed data memory with (address, value) pairs, as LoadData(). The
+  // seed is kept and loaded again by every ResetModel(), so it survives
+  // RunProgram().
+  explicit TTAHarness(const std::vector<std::pair<uint32_t, uint32_t>>& data,
+                      const std::string& trace_file = "");
+  TTAHarness(TTAHarness&) = delete;
+  ~TTAHarness();
+
+  // Start writing an FST trace of every step to `filename`.
+  void OpenTrace(const std::string& filename);
+  void CloseTrace();
+
+  void Reset() { top_->rst_i = 1; }
+
+  // Where the core starts fetching from when it comes out of reset; 0
+  // unless set. Takes effect at the next reset, so set it before the
+  // first Step() or before ResetModel().
+  void SetResetVector(uint32_t addr) { top_->reset_vector_i = addr; }
+
+  // Put the model back into reset and clear instruction and data memory,
+  // leaving the harness ready for a new program; data memory then gets the
+  // constructor's seed, if any. The reset is released
+  // after the usual number of reset cycles once stepping resumes.
+  void ResetModel();
+
+  // Zero just data or just instruction memory, without touching the
+  // model; ResetModel() does both. Clearing instructions also drops any
+  // fetch hook. Use between runs, not mid-access.
+  void ClearData() { ram_.Clear(); }
+  void ClearInstructions();
+
+  // Make data memory stop answering: ready stays low until ClearData() or
+  // ResetModel(). Loads then stall forever, which RunWithWatchdog()
+  // reports. Stores are different: execute.sv doesn't wait for ready on a
+  // write, so the core retires them, but the hung memory drops the data.
+  // The bus has no error signal, so a hang is the only fault there is to
+  // inject.
+  void HoldDataReadyLow() { ram_.Hang(); }
+
+  // Fill data memory with `value`, e.g. 0xdeadbeef, rather than 0, so a
+  // program reading words it never wrote gets something recognizable.
+  // Clears data memory now and on every later ClearData() or
+  // ResetModel(), so call it before LoadData(). The constructor's seed is
+  // loaded again on top.
+  void SetUninitializedFill(uint32_t value);
+
+  StepResult Step();
+
+  /*
+   * Run until "pin" equals "val" or max_clocks has been reached.
+   * Returns true if the pin reached the intended value before the clock ran
+   * out.
+   */
+  template <typename T>
+  bool RunUntil(T* pin, T val, int max_clocks) {
+    int start_clocks = clock_gen_.cycles();
+    while (!Verilated::gotFinish()) {
+      Step();
+
+      if (*pin == val)
+        return true;
+      else if (clock_gen_.cycles() - start_clocks >= max_clocks)
+        return false;
+    }
+    return false;
+  }
+
+  /**
+   * Run until max_clocks cycles have executed.
+   */
+  int RunUntil(int max_clocks);
+
+  /**
+   * Run for `cycles` cycles, servicing each bus only while its valid line
+   * is asserted or its ready line still needs dropping.
+   * Produces the same results as RunUntil(max_clocks) at a fraction of the
+   * cost for long programs. Returns the number of cycles run.
+   * instructions_retired() keeps counting, but the traces, bus monitors,
+   * coverage and cycle histogram skip the batch; Step() and RunUntil()
+   * pick up from the next instruction fetched after it.
+   */
+  int RunBatch(int cycles);
+
+  /**
+   * Run for up to max_cycles cycles, giving up early if the instruction
+   * fetch address hasn't moved and instr_done_o hasn't risen for
+   * stall_cycles consecutive cycles. Returns the stall, if there was one.
+   */
+  std::optional<Stall> RunWithWatchdog(int max_cycles, int stall_cycles = 100);
+
+  // ResetModel(), Load() `program` at address 0 and run it until the core
+  // fetches the word just past its end. Returns the cycles used after
+  // reset was released, or nothing if the program hadn't finished within
+  // max_cycles of that.
+  std::optional<int> RunProgram(const Program& program, int max_cycles);
+
+  // Instruction memory is word addressed, as is the sequencer's PC: an
+  // instruction at `addr` with one operand word is followed by the next
+  // instruction at `addr + 2`. The program must fit in instruction memory.
+  void Load(const Program& program, uint32_t addr = 0);
+  void Load(const std::vector<uint32_t>& code, uint32_t addr = 0);
+  // Like Load(), but reports a program that won't fit through `error`
+  // rather than failing a CHECK. Nothing is loaded if it doesn't fit.
+  bool TryLoad(const Program& program, uint32_t addr, ProgramTooLarge* error);
+  // Assembles `program` for `addr`, so label targets point into the loaded
+  // copy wherever it goes.
+  void LoadLabeled(const LabeledProgram& program, uint32_t addr = 0);
+
+  // Serve every instruction fetch from `hook`, called with the word
+  // address, instead of from loaded instruction memory; for generated or
+  // endless instruction streams. Like any FunctionBackend it may be called
+  // more than once per address. Replaces any earlier hook.
+  void SetInstructionFetchHook(std::function<uint32_t(uint32_t)> hook);
+  // Go back to serving fetches from instruction memory.
+  // ClearInstructions() and ResetModel() do this too.
+  void ClearInstructionFetchHook();
+
+  // Seed data memory with (address, value) pairs.
+  void LoadData(const std::vector<std::pair<uint32_t, uint32_t>>& entries);
+  void LoadDataMap(const std::map<uint32_t, uint32_t>& entries);
+
+  // (address, value) for every data memory word in [start, end).
+  std::vector<std::pair<uint32_t, uint32_t>> DumpDataRange(uint32_t start,
+                                                           uint32_t end);
+
+  // Start recording every data memory access Step() sees, in order. Runs
+  // through RunBatch() aren't recorded. ResetModel() clears the record.
+  void EnableMemTrace() { mem_trace_enabled_ = true; }
+  const std::vector<MemAccess>& mem_trace() const { return mem_trace_; }
+  // The recorded accesses to `addr`.
+  std::vector<MemAccess> MemTraceFor(uint32_t addr) const;
+
+  // Start recording every move Step() sees complete, in order, with the
+  // value it transported, e.g. 666 for a move of #666 into a register. Runs
+  // through RunBatch() aren't recorded. ResetModel() clears the record.
+  void EnableMoveTrace() { move_trace_enabled_ = true; }
+  const std::vector<MoveTrace>& move_trace() const { return move_trace_; }
+
+  MachineState DumpState();
+
+  // Listing of instruction memory as tta_tool disasm prints it, one
+  // "addr: move" line per instruction, from address 0 up to the last
+  // non-zero word. A move whose operands run off the end of memory is
+  // listed as the decode error instead.
+  std::string DisassembleLoaded();
+
+  // Every entry of `expected`, as (address, value), that data memory
+  // doesn't match, in the order given.
+  std::vector<MemoryMismatch> CompareData(
+      const std::vector<std::pair<uint32_t, uint32_t>>& expected);
+
+  // Golden port traces: every testtop port, sampled after each Step(), one
+  // line per step in hex. Unlike an FST trace these are easy to compare
+  // programmatically, for catching RTL regressions.
+  //
+  // Record while running a program, then later set the harness up the same
+  // way (same program, data and reset state) and replay: the harness is
+  // stepped once per recorded line and the first output port that differs
+  // is reported. Recording stops with StopPortRecording() or when the
+  // harness is destroyed.
+  void StartPortRecording(const std::string& path);
+  void StopPortRecording();
+  std::optional<PortMismatch> ReplayAndCompare(const std::string& path);
+
+  // The bus signals as they stand now, e.g. after a Step(), for
+  // protocol assertions that would otherwise read the ports one by one.
+  BusSnapshot PeekBus() const;
+
+  // Handshake checks on each bus, fed by Step() but not RunBatch().
+  // ResetModel() clears them.
+  const BusMonitor& instr_bus_monitor() const { return instr_monitor_; }
+  const BusMonitor& data_bus_monitor() const { return data_monitor_; }
+
+  // Data memory word at `addr`, read as a two's complement value.
+  int32_t DataSigned(uint32_t addr) {
+    CHECK_LT(addr, ram_.mem().size());
+    return (int32_t)ram_.mem()[addr];
+  }
+
+  // Number of moves completed, i.e. rising edges of instr_done_o, since
+  // the harness was created or last ResetModel().
+  int instructions_retired() const { return instructions_retired_; }
+  // Harness cycle of the most recent of those rising edges, if any.
+  std::optional<int> last_done_cycle() const { return last_done_cycle_; }
+
+  // Cycles stepped through Step(), by the destination unit of the move
+  // being fetched or executed at the time. A move is in flight from the
+  // start of its opcode fetch until the next one starts. ResetModel()
+  // clears it.
+  const std::map<Unit, int>& cycle_histogram() const {
+    return cycle_histogram_;
+  }
+
+  // Accumulated over every program run on this harness, including across
+  // ResetModel().
+  const Coverage& coverage() const { return coverage_; }
+
+  Vtesttop* top() const { return top_.get(); }
+  const ClockGenerator& clk() const { return clock_gen_; }
+  RAMSim* ram() { return &ram_; }
+  RAMSim* prg() { return &prg_; }
+
+ private:
+  std::unique_ptr<Vtesttop> top_;
+  std::unique_ptr<VerilatedFstC> trace_;
+  ClockGenerator clock_gen_;
+  RAMSim prg_;
+  RAMSim ram_;
+
+  // testtop's ports, in the column order of a golden port trace.
+  std::vector<uint32_t> SamplePorts() const;
+
+  // Up to three words of instruction memory from `addr`, as fetches would
+  // see them: from the fetch hook if one is set.
+  std::vector<uint32_t> InstrWordsAt(uint32_t addr) const;
+
+  // Called with each opcode word as it is fetched.
+  void OnFetch(uint32_t op);
+  // Called after every clock step to count completed moves.
+  void CountRetired();
+
+  // Data bus state as of the last serviced cycle. Writes hold valid until
+  // the next move starts, so a new access can begin without valid ever
+  // dropping; it shows up as a change of address or strobes instead.
+  CData data_valid_ = 0;
+  CData data_wstrb_ = 0;
+  IData data_addr_ = 0;
+  // A read that has started but whose data isn't ready yet.
+  std::optional<MemAccess> pending_read_;
+
+  // Whether the instruction bus was fetching an opcode, and from where, as
+  // of the last serviced cycle. Used to spot the start of each fetch.
+  bool fetching_opcode_ = false;
+  IData fetch_addr_ = 0;
+
+  Coverage coverage_;
+
+  // Destination and opcode address of the move in flight, once the first
+  // fetch has started.
+  std::optional<Unit> in_flight_dst_;
+  IData in_flight_addr_ = 0;
+  std::map<Unit, int> cycle_histogram_;
+
+  CData last_done_ = 0;
+  int instructions_retired_ = 0;
+  std::optional<int> last_done_cycle_;
+
+  BusMonitor instr_monitor_;
+  BusMonitor data_monitor_;
+
+  std::unique_ptr<std::ofstream> port_trace_;
+
+  // Data memory contents ResetModel() restores.
+  std::vector<std::pair<uint32_t, uint32_t>> data_seed_;
+
+  std::function<uint32_t(uint32_t)> fetch_hook_;
+  std::unique_ptr<FunctionBackend> fetch_hook_backend_;
+
+  bool mem_trace_enabled_ = false;
+  std::vector<MemAccess> mem_trace_;
+
+  bool move_trace_enabled_ = false;
+  std::vector<MoveTrace> move_trace_;
+
+  CData c_gnd_ = 0;
+  IData i_gnd_ = 0;
+};
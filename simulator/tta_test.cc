@@ -1,13 +1,24 @@
 #include <glog/logging.h>
 #include <gtest/gtest.h>
-#include <verilated_fst_c.h>
 
+#include <algorithm>
+#include <bitset>
+#include <fstream>
+#include <iterator>
 #include <memory>
+#include <random>
+#include <set>
+#include <sstream>
+#include <tuple>
+#include <unordered_map>
+#include <unordered_set>
 
-#include "Vtesttop.h"
 #include "assembler.h"
-#include "clock_gen.h"
-#include "ram_sim.h"
+#include "debugger.h"
+#include "move.h"
+#include "optimizer.h"
+#include "tta_harness.h"
+#include "validator.h"
 
 // A kind of integration tests that runs through some common
 // operations and checks their results.
@@ -16,30 +27,10 @@
 // TODO: unit tests which run against the individual components
 // (Execute/Decode/Sequencer etc) rather than the top level.
 
-class TTATest : public ::testing::Test {
- public:
-  TTATest()
-      : top_(std::make_unique<Vtesttop>()),
-        clock_gen_(1, 1 /* reset_cycles */, &top_->rst_i, &top_->sysclk_i),
-        prg_(1024,
-             c_gnd_,
-             top_->instr_valid_o,
-             &top_->instr_ready_i,
-             &top_->instr_data_read_i,
-             i_gnd_,
-             top_->instr_addr_o),
-        ram_(1024,
-             top_->data_wstrb_o,
-             top_->data_valid_o,
-             &top_->data_ready_i,
-             &top_->data_data_read_i,
-             top_->data_data_write_o,
-             top_->data_addr_o) {}
-
+class TTATest : public ::testing::Test, public TTAHarness {
  protected:
   void SetUp() override {
     Reset();
-    Verilated::traceEverOn(true);
     std::string trace_name = ::testing::UnitTest::GetInstance()
                                  ->current_test_info()
                                  ->test_case_name();
@@ -47,89 +38,35 @@ class TTATest : public ::testing::Test {
     trace_name.append(
         ::testing::UnitTest::GetInstance()->current_test_info()->name());
     trace_name.append(".vcd");
-    trace_ = std::make_unique<VerilatedFstC>();
-    top_->trace(trace_.get(), 99);
-    trace_->open(trace_name.c_str());
+    OpenTrace(trace_name);
   }
 
   void TearDown() override {
-    trace_->flush();
-    trace_->close();
-  }
-
- public:
-  void Reset() { top_->rst_i = 1; }
-
-  void Step() {
-    clock_gen_.Step(trace_.get());
-    top_->eval();
-    if (!top_->rst_i & clock_gen_.Bus()) {
-      ram_.Do();
-      prg_.Do();
-    }
-  }
-
-  /*
-   * Run until "pin" equals "val" or max_clocks has been reached.
-   * Returns true if the pin reached the intended value before the clock ran
-   * out.
-   */
-  template <typename T>
-  bool RunUntil(T* pin, T val, int max_clocks) {
-    int start_clocks = clock_gen_.cycles();
-    while (!Verilated::gotFinish()) {
-      Step();
-
-      if (*pin == val || clock_gen_.cycles())
-        return true;
-      else if (clock_gen_.cycles() - start_clocks >= max_clocks)
-        return false;
-    }
-    return false;
-  }
-
-  /**
-   * Run until max_clocks cycles have executed.
-   */
-  int RunUntil(int max_clocks) {
-    int start_clk = clock_gen_.cycles();
-    while (!Verilated::gotFinish() &&
-           (clock_gen_.cycles() < max_clocks + start_clk)) {
-      Step();
-    }
-    return clock_gen_.cycles() - start_clk;
+    LOG(INFO) << "Coverage: " << coverage();
+    CloseTrace();
   }
 
-  void Load(const Program& program, uint32_t addr = 0) {
-    off_t pos = addr;
-    for (auto& instr : program) {
-      std::vector<uint32_t> code = instr.assemble();
-      for (const auto& op : code) {
-        prg_.mem()[pos++] = op;
-      }
+  // Checks every (address, value) in `expected` against data memory and
+  // lists all the mismatches, e.g.
+  //   EXPECT_TRUE(DataMatches({{100, 1}, {101, 2}}));
+  ::testing::AssertionResult DataMatches(
+      const std::vector<std::pair<uint32_t, uint32_t>>& expected) {
+    std::vector<MemoryMismatch> mismatches = CompareData(expected);
+    if (mismatches.empty())
+      return ::testing::AssertionSuccess();
+    ::testing::AssertionResult result = ::testing::AssertionFailure();
+    for (const auto& m : mismatches) {
+      result << "\n  [" << m.addr << "] expected " << FormatWord(m.expected)
+             << ", got " << FormatWord(m.actual);
     }
+    return result;
   }
-
- protected:
-  std::unique_ptr<VerilatedFstC> trace_;
-
-  Vtesttop* top() const { return top_.get(); }
-  const ClockGenerator& clk() const { return clock_gen_; }
-  RAMSim* ram() { return &ram_; }
-  RAMSim* prg() { return &prg_; }
-
- private:
-  std::unique_ptr<Vtesttop> top_;
-  ClockGenerator clock_gen_;
-  RAMSim prg_;
-  RAMSim ram_;
-
-  CData c_gnd_ = 0;
-  IData i_gnd_ = 0;
 };
 
 TEST_F(TTATest, Initialize) {
-  ASSERT_TRUE(RunUntil(&top()->rst_i, (CData)1, 1));
+  EXPECT_TRUE(RunUntil(&top()->rst_i, (CData)0, 4));
+  // Reset stays released, so waiting for it again runs out the clock.
+  EXPECT_FALSE(RunUntil(&top()->rst_i, (CData)1, 4));
 }
 
 
@@ -146,11 +83,12 @@ TEST_F(TTATest, RegisterSetAbsMemorySetAbs) {
             .Si(0)
             .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
             .Di(123)});
-  ASSERT_TRUE(RunUntil(&top()->rst_i, (CData)1, 1));  // Clear the reset
+  Step();  // First clock, still in reset
 
   EXPECT_EQ(RunUntil(8), 8); /* no more than 8 clocks used */
 
   EXPECT_EQ(top()->rst_i, 0);
+  EXPECT_EQ(instructions_retired(), 2);
   EXPECT_EQ(ram()->mem()[123], 666);
 }
 
@@ -160,7 +98,7 @@ TEST_F(TTATest, MemImmediateToMemImmediate) {
             .Si(123)
             .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
             .Di(124)});
-  ASSERT_TRUE(RunUntil(&top()->rst_i, (CData)1, 1));  // Clear the reset
+  Step();  // First clock, still in reset
   ram()->mem()[123] = 666;
   RunUntil(25);
   EXPECT_EQ(ram()->mem()[124], 666);
@@ -172,7 +110,7 @@ TEST_F(TTATest, MemOperandToMemOperand) {
             .Soperand(123)
             .Dst(Unit::UNIT_MEMORY_OPERAND)
             .Doperand(124)});
-  ASSERT_TRUE(RunUntil(&top()->rst_i, (CData)1, 1));  // Clear the reset
+  Step();  // First clock, still in reset
   ram()->mem()[123] = 666;
   RunUntil(25);
   EXPECT_EQ(ram()->mem()[124], 666);
@@ -184,7 +122,7 @@ TEST_F(TTATest, PointerValToMemImmediate) {
     Instr().Src(Unit::UNIT_ABS_IMMEDIATE).Si(123).Dst(Unit::UNIT_REGISTER).Di(1),
     Instr().Src(Unit::UNIT_REGISTER_POINTER).Si(1).Dst(Unit::UNIT_MEMORY_IMMEDIATE).Di(124)
   });
-  ASSERT_TRUE(RunUntil(&top()->rst_i, (CData)1, 1));  // Clear the reset
+  Step();  // First clock, still in reset
   RunUntil(100);
   EXPECT_EQ(ram()->mem()[124], 666);
 }
@@ -200,7 +138,7 @@ TEST_F(TTATest, MemOperandToRegisterToMemoryOperand) {
             .Si(0)
             .Dst(Unit::UNIT_MEMORY_OPERAND)
             .Doperand(124)});
-  ASSERT_TRUE(RunUntil(&top()->rst_i, (CData)1, 1));  // Clear the reset
+  Step();  // First clock, still in reset
   ram()->mem()[123] = 666;
   RunUntil(25);
   EXPECT_EQ(ram()->mem()[124], 666);
@@ -228,13 +166,2417 @@ TEST_F(TTATest, AluAddition) {
             .Si(0)
             .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
             .Di(123)});
-  ASSERT_TRUE(RunUntil(&top()->rst_i, (CData)1, 1));  // Clear the reset
+  Step();  // First clock, still in reset
 
   EXPECT_EQ(RunUntil(17), 17); /* no more than 16 clocks used */
 
   EXPECT_TRUE(top()->instr_done_o);
   EXPECT_EQ(top()->rst_i, 0);
   EXPECT_EQ(ram()->mem()[123], 777);
+
+  // The core may also have fetched the (all UNIT_NONE) word past the end.
+  std::set<Unit> units = coverage().units_used;
+  units.erase(Unit::UNIT_NONE);
+  EXPECT_EQ(units,
+            std::set<Unit>({Unit::UNIT_ABS_IMMEDIATE, Unit::UNIT_ALU_LEFT,
+                            Unit::UNIT_ALU_RIGHT, Unit::UNIT_ALU_OPERATOR,
+                            Unit::UNIT_ALU_RESULT,
+                            Unit::UNIT_MEMORY_IMMEDIATE}));
+  EXPECT_EQ(coverage().alu_ops_used, std::set<ALUOp>({ALUOp::ALU_ADD}));
+}
+
+TEST_F(TTATest, AluSubtractionGoesNegative) {
+  Load({Instr()
+            .Src(Unit::UNIT_ABS_IMMEDIATE)
+            .Si(111)
+            .Dst(Unit::UNIT_ALU_LEFT)
+            .Di(0),
+        Instr()
+            .Src(Unit::UNIT_ABS_IMMEDIATE)
+            .Si(666)
+            .Dst(Unit::UNIT_ALU_RIGHT)
+            .Di(0),
+        Instr()
+            .Src(Unit::UNIT_ABS_IMMEDIATE)
+            .Si((int)ALUOp::ALU_SUB)
+            .Dst(Unit::UNIT_ALU_OPERATOR)
+            .Di(0),
+        Instr()
+            .Src(Unit::UNIT_ALU_RESULT)
+            .Si(0)
+            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Di(123)});
+  Step();  // First clock, still in reset
+  RunUntil(50);
+  EXPECT_EQ(DataSigned(123), -555) << FormatWord(ram()->mem()[123]);
+}
+
+// A single model can run several programs back to back.
+TEST_F(TTATest, ResetModelBetweenPrograms) {
+  Load({Instr()
+            .Src(Unit::UNIT_ABS_IMMEDIATE)
+            .Si(666)
+            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Di(123)});
+  Step();  // First clock, still in reset
+  RunUntil(25);
+  EXPECT_EQ(ram()->mem()[123], 666);
+
+  ResetModel();
+  EXPECT_EQ(top()->rst_i, 1);
+  EXPECT_EQ(ram()->mem()[123], 0);
+
+  Load({Instr()
+            .Src(Unit::UNIT_ABS_IMMEDIATE)
+            .Si(555)
+            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Di(124)});
+  RunUntil(25);
+  EXPECT_EQ(top()->rst_i, 0);
+  EXPECT_EQ(ram()->mem()[123], 0);
+  EXPECT_EQ(ram()->mem()[124], 555);
+}
+
+// TODO: set/get PC, stack, other ALU ops
+
+// The batched run path must leave the machine in the same state as
+// stepping one cycle at a time.
+TEST_F(TTATest, RunBatchMatchesRunUntil) {
+  Program program;
+  for (int i = 0; i < 16; i++) {
+    program.Mov(Unit::UNIT_ABS_IMMEDIATE, 100 + i, Unit::UNIT_MEMORY_IMMEDIATE,
+                200 + i);
+  }
+  Load(program);
+  Step();  // First clock, still in reset
+  RunUntil(200);
+  std::vector<IData> stepped = ram()->mem();
+
+  ResetModel();
+  Load(program);
+  EXPECT_EQ(RunBatch(201), 201);
+  EXPECT_EQ(ram()->mem(), stepped);
+  EXPECT_EQ(ram()->mem()[215], 115);
+}
+
+// Stepping after a batch picks up where the batch left off: no access
+// already underway is reported again and no retirement is lost.
+TEST_F(TTATest, StepAfterRunBatchMatchesRunUntil) {
+  Program program;
+  for (int i = 0; i < 16; i++) {
+    program.Mov(Unit::UNIT_ABS_IMMEDIATE, 100 + i, Unit::UNIT_MEMORY_IMMEDIATE,
+                200 + i);
+  }
+  EnableMemTrace();
+  Load(program);
+  Step();  // First clock, still in reset
+  RunUntil(200);
+  std::vector<MemAccess> stepped = mem_trace();
+  int retired = instructions_retired();
+
+  ResetModel();
+  Load(program);
+  int batch_end = RunBatch(100);
+  RunUntil(101);
+  EXPECT_EQ(instructions_retired(), retired);
+  std::vector<MemAccess> tail;
+  for (const MemAccess& access : stepped) {
+    if (access.cycle > batch_end)
+      tail.push_back(access);
+  }
+  ASSERT_EQ(mem_trace().size(), tail.size());
+  for (size_t i = 0; i < tail.size(); i++) {
+    EXPECT_EQ(mem_trace()[i].cycle, tail[i].cycle);
+    EXPECT_EQ(mem_trace()[i].addr, tail[i].addr);
+  }
+}
+
+TEST_F(TTATest, JumpSkipsInstruction) {
+  Load(AssembleLabeled({Instr::Jump("skip"),
+                        Instr()
+                            .Src(Unit::UNIT_ABS_IMMEDIATE)
+                            .Si(111)
+                            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+                            .Di(123),
+                        Instr::Label("skip"),
+                        Instr()
+                            .Src(Unit::UNIT_ABS_IMMEDIATE)
+                            .Si(666)
+                            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+                            .Di(124)}));
+  Step();  // First clock, still in reset
+  RunUntil(50);
+  EXPECT_EQ(ram()->mem()[123], 0);
+  EXPECT_EQ(ram()->mem()[124], 666);
+}
+
+// Count R0 down from 3, adding one to mem[100] on each pass. The branch
+// back to "loop" or out to "done" is picked from a jump table at mem[10..11]
+// indexed by (R0 == 0).
+TEST_F(TTATest, BackwardBranchLoopTerminates) {
+  auto set = [](Unit dst, short di, short val) {
+    return Instr().Src(Unit::UNIT_ABS_IMMEDIATE).Si(val).Dst(dst).Di(di);
+  };
+  auto move = [](Unit src, short si, Unit dst, short di) {
+    return Instr().Src(src).Si(si).Dst(dst).Di(di);
+  };
+  Load(AssembleLabeled({
+      LabeledInstr::WithTarget(Instr()
+                                   .Src(Unit::UNIT_ABS_OPERAND)
+                                   .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+                                   .Di(10),
+                               "loop"),
+      LabeledInstr::WithTarget(Instr()
+                                   .Src(Unit::UNIT_ABS_OPERAND)
+                                   .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+                                   .Di(11),
+                               "done"),
+      set(Unit::UNIT_REGISTER, 0, 3),
+      set(Unit::UNIT_ALU_RIGHT, 0, 1),
+      set(Unit::UNIT_ALU_OPERATOR, 0, (short)ALUOp::ALU_SUB),
+      set(Unit::UNIT_ALU_RIGHT, 1, 1),
+      set(Unit::UNIT_ALU_OPERATOR, 1, (short)ALUOp::ALU_ADD),
+      set(Unit::UNIT_ALU_RIGHT, 2, 0),
+      set(Unit::UNIT_ALU_OPERATOR, 2, (short)ALUOp::ALU_EQL),
+      set(Unit::UNIT_ALU_RIGHT, 3, 10),
+      set(Unit::UNIT_ALU_OPERATOR, 3, (short)ALUOp::ALU_ADD),
+      Instr::Label("loop"),
+      move(Unit::UNIT_REGISTER, 0, Unit::UNIT_ALU_LEFT, 0),
+      move(Unit::UNIT_ALU_RESULT, 0, Unit::UNIT_REGISTER, 0),
+      move(Unit::UNIT_MEMORY_IMMEDIATE, 100, Unit::UNIT_ALU_LEFT, 1),
+      move(Unit::UNIT_ALU_RESULT, 1, Unit::UNIT_MEMORY_IMMEDIATE, 100),
+      move(Unit::UNIT_REGISTER, 0, Unit::UNIT_ALU_LEFT, 2),
+      move(Unit::UNIT_ALU_RESULT, 2, Unit::UNIT_ALU_LEFT, 3),
+      move(Unit::UNIT_ALU_RESULT, 3, Unit::UNIT_REGISTER, 1),
+      move(Unit::UNIT_REGISTER_POINTER, 1, Unit::UNIT_PC, 0),
+      Instr::Label("done"),
+      set(Unit::UNIT_MEMORY_IMMEDIATE, 101, 666),
+  }));
+  Step();  // First clock, still in reset
+  RunUntil(1000);
+  EXPECT_EQ(ram()->mem()[100], 3);
+  EXPECT_EQ(ram()->mem()[101], 666);
+}
+
+// Branch on a < b, checking both the taken and not-taken paths on one
+// model.
+TEST_F(TTATest, BranchIfLessThan) {
+  auto program = [](short a, short b) {
+    LabeledProgram program = {
+        Instr()
+            .Src(Unit::UNIT_ABS_IMMEDIATE)
+            .Si(a)
+            .Dst(Unit::UNIT_ALU_LEFT)
+            .Di(0),
+        Instr()
+            .Src(Unit::UNIT_ABS_IMMEDIATE)
+            .Si(b)
+            .Dst(Unit::UNIT_ALU_RIGHT)
+            .Di(0),
+        Instr()
+            .Src(Unit::UNIT_ABS_IMMEDIATE)
+            .Si((short)ALUOp::ALU_LT)
+            .Dst(Unit::UNIT_ALU_OPERATOR)
+            .Di(0)};
+    LabeledProgram branch = BranchIf(0, "less");
+    program.insert(program.end(), branch.begin(), branch.end());
+    program.insert(program.end(), {Instr()
+                                       .Src(Unit::UNIT_ABS_IMMEDIATE)
+                                       .Si(1)
+                                       .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+                                       .Di(100),
+                                   Instr::Jump("end"),
+                                   Instr::Label("less"),
+                                   Instr()
+                                       .Src(Unit::UNIT_ABS_IMMEDIATE)
+                                       .Si(2)
+                                       .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+                                       .Di(100),
+                                   Instr::Label("end")});
+    return AssembleLabeled(program);
+  };
+
+  Load(program(1, 2));
+  Step();  // First clock, still in reset
+  RunUntil(100);
+  EXPECT_EQ(ram()->mem()[100], 2);
+
+  ResetModel();
+  Load(program(2, 1));
+  RunUntil(100);
+  EXPECT_EQ(ram()->mem()[100], 1);
+}
+
+TEST(AssemblerTest, RejectsIllegalDirections) {
+  std::vector<uint32_t> prg;
+  AssembleError error;
+  EXPECT_FALSE(Instr()
+                   .Src(Unit::UNIT_PC_IF)
+                   .Si(0)
+                   .Dst(Unit::UNIT_REGISTER)
+                   .Di(0)
+                   .TryAssemble(&prg, &error));
+  EXPECT_EQ(error.kind, AssembleError::Kind::ILLEGAL_DIRECTION);
+  EXPECT_EQ(error.unit, Unit::UNIT_PC_IF);
+  EXPECT_EQ(error.role, Role::SOURCE);
+
+  EXPECT_FALSE(Instr()
+                   .Src(Unit::UNIT_REGISTER)
+                   .Si(0)
+                   .Dst(Unit::UNIT_ABS_IMMEDIATE)
+                   .Di(0)
+                   .TryAssemble(&prg, &error));
+  EXPECT_EQ(error.kind, AssembleError::Kind::ILLEGAL_DIRECTION);
+  EXPECT_EQ(error.unit, Unit::UNIT_ABS_IMMEDIATE);
+  EXPECT_EQ(error.role, Role::DESTINATION);
+
+  EXPECT_FALSE(Instr()
+                   .Src(Unit::UNIT_MEMORY_OPERAND)
+                   .Dst(Unit::UNIT_REGISTER)
+                   .Di(0)
+                   .TryAssemble(&prg, &error));
+  EXPECT_EQ(error.kind, AssembleError::Kind::MISSING_OPERAND);
+
+  EXPECT_TRUE(Instr()
+                  .Src(Unit::UNIT_ALU_LEFT)
+                  .Si(0)
+                  .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+                  .Di(1)
+                  .TryAssemble(&prg, &error));
+  EXPECT_EQ(prg.size(), 1);
+}
+
+TEST_F(TTATest, ProgramBuilderAlu) {
+  Program program;
+  program.Mov(Unit::UNIT_ABS_IMMEDIATE, 666, Unit::UNIT_REGISTER, 0)
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, 111, Unit::UNIT_REGISTER, 1)
+      .Alu(0, 1, ALUOp::ALU_ADD, 2)
+      .Mov(Unit::UNIT_ALU_RESULT, 2, Unit::UNIT_MEMORY_IMMEDIATE, 123)
+      .Add(Instr()
+               .Src(Unit::UNIT_ABS_IMMEDIATE)
+               .Si(1)
+               .Dst(Unit::UNIT_MEMORY_OPERAND)
+               .Doperand(124));
+  EXPECT_EQ(program.size(), 7);
+  EXPECT_EQ(program.LenWords(), 8);
+  EXPECT_EQ(program.assemble().size(), program.LenWords());
+
+  Load(program);
+  Step();  // First clock, still in reset
+  RunUntil(100);
+  EXPECT_EQ(ram()->mem()[123], 777);
+  EXPECT_EQ(ram()->mem()[124], 1);
+}
+
+// Drives a RAMSim's bus ports directly, without a model.
+class RAMSimTest : public ::testing::Test {
+ public:
+  RAMSimTest()
+      : ram_(0, wstrb_, valid_, &ready_, &read_data_, write_data_, addr_) {}
+
+ protected:
+  IData Read(IData addr) {
+    valid_ = 1;
+    wstrb_ = 0;
+    addr_ = addr;
+    ram_.Do();
+    valid_ = 0;
+    ram_.Do();
+    return read_data_;
+  }
+
+  void Write(IData addr, IData data) {
+    valid_ = 1;
+    wstrb_ = 0xf;
+    addr_ = addr;
+    write_data_ = data;
+    ram_.Do();
+    valid_ = 0;
+    ram_.Do();
+  }
+
+  CData wstrb_ = 0, valid_ = 0, ready_ = 0;
+  IData read_data_ = 0, write_data_ = 0, addr_ = 0;
+  RAMSim ram_;
+};
+
+TEST_F(RAMSimTest, RegionsAndUnmappedAccesses) {
+  RAMBackend ram(256);
+  ram_.AddRegion("ram", 0x100, 256, &ram);
+  ram_.SetFaultValue(0xdeadbeef);
+
+  Write(0x100, 666);
+  Write(0x1ff, 777);
+  EXPECT_EQ(ram.mem()[0], 666);
+  EXPECT_EQ(ram.mem()[0xff], 777);
+  EXPECT_EQ(Read(0x100), 666);
+  EXPECT_TRUE(ram_.unmapped().empty());
+
+  EXPECT_EQ(Read(0xff), 0xdeadbeef);
+  Write(0x200, 1);
+  EXPECT_EQ(ram_.unmapped(), std::vector<IData>({0xff, 0x200}));
+}
+
+TEST_F(RAMSimTest, DevicesSeeEachWriteOnce) {
+  UARTBackend uart;
+  ram_.AddRegion("uart", 0x10, 1, &uart);
+  // Hold valid for several cycles, as the core does after a store.
+  valid_ = 1;
+  wstrb_ = 0xf;
+  addr_ = 0x10;
+  write_data_ = 'H';
+  for (int i = 0; i < 3; i++)
+    ram_.Do();
+  valid_ = 0;
+  ram_.Do();
+  Write(0x10, 'I');
+  EXPECT_EQ(uart.output(), "HI");
+}
+
+// With the flat memory unmapped, the harness's data bus reaches only the
+// RAM at 0x100; a load from 0x50 is reported and reads the fault value.
+TEST_F(TTATest, UnmappedBelowFlatMemorySize) {
+  RAMBackend backend(256);
+  ram()->RemoveRegion(RAMSim::kFlatRegion);
+  ram()->AddRegion("ram", 0x100, 256, &backend);
+  ram()->SetFaultValue(0xdeadbeef);
+  Program program =
+      Program()
+          .Mov(Unit::UNIT_MEMORY_IMMEDIATE, 0x50, Unit::UNIT_REGISTER, 0)
+          .Mov(Unit::UNIT_REGISTER, 0, Unit::UNIT_MEMORY_IMMEDIATE, 0x100);
+  ASSERT_EQ(ram()->mem().size(), 1024);
+  ASSERT_TRUE(RunProgram(program, 100).has_value());
+  EXPECT_EQ(ram()->unmapped(), std::vector<IData>{0x50});
+  EXPECT_EQ(backend.mem()[0], 0xdeadbeef);
+}
+
+// A harness constructed with a trace file writes it out when destroyed,
+// without any explicit close.
+TEST(TTAHarnessTest, TraceClosedOnDestruction) {
+  const std::string trace_name = "TTAHarnessTest-TraceClosedOnDestruction.fst";
+  {
+    TTAHarness harness(trace_name);
+    harness.Reset();
+    harness.RunUntil(10);
+  }
+  std::ifstream trace(trace_name, std::ios::binary | std::ios::ate);
+  ASSERT_TRUE(trace.is_open());
+  EXPECT_GT(trace.tellg(), 0);
+}
+
+TEST(TTAHarnessTest, SeededWithData) {
+  TTAHarness harness({{123, 666}, {124, 1}});
+  EXPECT_EQ(harness.ram()->mem()[123], 666);
+  EXPECT_EQ(harness.DumpDataRange(123, 125),
+            (std::vector<std::pair<uint32_t, uint32_t>>{{123, 666}, {124, 1}}));
+
+  // RunProgram() resets the model; the seed is still there for the program
+  // to read.
+  ASSERT_TRUE(harness
+                  .RunProgram(Program().Mov(Unit::UNIT_MEMORY_IMMEDIATE, 123,
+                                            Unit::UNIT_MEMORY_IMMEDIATE, 125),
+                              50)
+                  .has_value());
+  EXPECT_EQ(harness.ram()->mem()[125], 666);
+  EXPECT_EQ(harness.ram()->mem()[124], 1);
+}
+
+TEST_F(TTATest, WatchdogReportsStall) {
+  // The second move waits forever on a data memory that has stopped
+  // answering.
+  Load({Instr()
+            .Src(Unit::UNIT_ABS_IMMEDIATE)
+            .Si(666)
+            .Dst(Unit::UNIT_REGISTER)
+            .Di(0),
+        Instr()
+            .Src(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Si(123)
+            .Dst(Unit::UNIT_REGISTER)
+            .Di(1)});
+  ram()->Hang();
+  Step();  // First clock, still in reset
+  std::optional<Stall> stall = RunWithWatchdog(1000, 20);
+  ASSERT_TRUE(stall.has_value());
+  EXPECT_EQ(stall->pc, 1);
+  EXPECT_LT(stall->cycle, 100);
+  EXPECT_EQ(instructions_retired(), 1);
+}
+
+TEST_F(TTATest, UnresponsiveMemoryStallsLoadsOnly) {
+  Load(Program()
+           .Mov(Unit::UNIT_ABS_IMMEDIATE, 666, Unit::UNIT_MEMORY_IMMEDIATE, 10)
+           .Mov(Unit::UNIT_MEMORY_IMMEDIATE, 10, Unit::UNIT_REGISTER, 0)
+           .Mov(Unit::UNIT_ABS_IMMEDIATE, 1, Unit::UNIT_REGISTER, 1));
+  HoldDataReadyLow();
+  Step();  // First clock, still in reset
+
+  // The store retires without ready, though its data is lost; the load
+  // never retires.
+  RunUntil(20);
+  ASSERT_EQ(instructions_retired(), 1);
+  EXPECT_EQ(ram()->mem()[10], 0);
+  int done_cycle = *last_done_cycle();
+  RunUntil(500);
+  EXPECT_EQ(instructions_retired(), 1);
+  EXPECT_EQ(last_done_cycle(), done_cycle);
+  EXPECT_FALSE(top()->instr_done_o);
+}
+
+TEST_F(TTATest, PeekBusSeesAStore) {
+  Load(Program()
+           .Mov(Unit::UNIT_ABS_IMMEDIATE, 666, Unit::UNIT_REGISTER, 0)
+           .Mov(Unit::UNIT_REGISTER, 0, Unit::UNIT_MEMORY_IMMEDIATE, 123));
+  Step();  // First clock, still in reset
+
+  std::optional<BusSnapshot> store;
+  for (int i = 0; i < 30 && !store; i++) {
+    Step();
+    BusSnapshot bus = PeekBus();
+    EXPECT_EQ(bus.instr_addr, top()->instr_addr_o);
+    EXPECT_EQ(bus.data_valid, (bool)top()->data_valid_o);
+    if (bus.data_valid && bus.data_wstrb)
+      store = bus;
+  }
+  ASSERT_TRUE(store.has_value());
+  EXPECT_EQ(store->data_addr, 123);
+  EXPECT_EQ(store->data_write, 666);
+  EXPECT_EQ(store->data_wstrb, 0xf);
+}
+
+TEST(TTAHarnessTest, FormatWord) {
+  EXPECT_EQ(FormatWord(0), "0x00000000 (0, 0)");
+  EXPECT_EQ(FormatWord(666), "0x0000029a (666, 666)");
+  EXPECT_EQ(FormatWord(0xffffffff), "0xffffffff (4294967295, -1)");
+}
+
+TEST(AssemblerTest, AllALUOpsInOpcodeOrder) {
+  int expected = 0;
+  for (ALUOp op : kAllALUOps)
+    EXPECT_EQ((int)op, expected++);
+  EXPECT_EQ(expected, 24);
+}
+
+TEST_F(TTATest, StepResultReportsBusActivity) {
+  Load({Instr()
+            .Src(Unit::UNIT_ABS_IMMEDIATE)
+            .Si(666)
+            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Di(123),
+        Instr()
+            .Src(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Si(123)
+            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Di(124)});
+  Step();  // First clock, still in reset
+
+  std::vector<std::pair<uint32_t, uint32_t>> reads, writes;
+  std::vector<uint32_t> fetches;
+  for (int i = 0; i < 100; i++) {
+    StepResult result = Step();
+    if (result.data_read)
+      reads.push_back(*result.data_read);
+    if (result.data_write)
+      writes.push_back(*result.data_write);
+    if (result.instr_fetch)
+      fetches.push_back(*result.instr_fetch);
+  }
+  EXPECT_EQ(reads, (std::vector<std::pair<uint32_t, uint32_t>>{{123, 666}}));
+  EXPECT_EQ(writes, (std::vector<std::pair<uint32_t, uint32_t>>{
+                        {123, 666}, {124, 666}}));
+  ASSERT_GE(fetches.size(), 2);
+  EXPECT_EQ(fetches[0], 0);
+  EXPECT_EQ(fetches[1], 1);
+}
+
+TEST(AssemblerTest, DecodeAndDisassemble) {
+  Program program = {Instr()
+                         .Src(Unit::UNIT_ABS_IMMEDIATE)
+                         .Si(666)
+                         .Dst(Unit::UNIT_REGISTER)
+                         .Di(1),
+                     Instr()
+                         .Src(Unit::UNIT_MEMORY_OPERAND)
+                         .Soperand(123)
+                         .Dst(Unit::UNIT_ALU_LEFT)
+                         .Di(2),
+                     Instr()
+                         .Src(Unit::UNIT_ALU_RESULT)
+                         .Si(2)
+                         .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+                         .Di(124),
+                     Instr()};
+  std::vector<uint32_t> words = program.assemble();
+
+  std::vector<std::string> listing;
+  size_t pos = 0;
+  while (pos < words.size()) {
+    size_t len;
+    std::ostringstream os;
+    os << Instr::Decode(words, pos, &len);
+    listing.push_back(os.str());
+    pos += len;
+  }
+  EXPECT_EQ(listing, std::vector<std::string>({"R01 := #29a",
+                                               "ALU2:LEFT := *(0000007b)",
+                                               "*(07c) := ALU2:RESULT",
+                                               "NOP"}));
+}
+
+TEST(AssemblerTest, ProgramBinaryRoundtrip) {
+  std::vector<uint32_t> words = {0x0029a0b3, 0xdeadbeef, 0};
+  const std::string path = "AssemblerTest-ProgramBinaryRoundtrip.bin";
+  ASSERT_TRUE(WriteProgramBinary(path, words));
+  EXPECT_EQ(ReadProgramBinary(path), words);
+  EXPECT_FALSE(ReadProgramBinary("does-not-exist.bin").has_value());
+}
+
+TEST(AssemblerTest, ProgramBinaryByteOrder) {
+  std::vector<uint32_t> words = {0x0029a0b3, 0xdeadbeef};
+  const std::string path = "AssemblerTest-ProgramBinaryByteOrder.bin";
+
+  ASSERT_TRUE(WriteProgramBinary(path, words, Endianness::BIG));
+  std::ifstream in(path, std::ios::binary);
+  std::vector<uint8_t> bytes((std::istreambuf_iterator<char>(in)),
+                             std::istreambuf_iterator<char>());
+  EXPECT_EQ(bytes, (std::vector<uint8_t>{0x00, 0x29, 0xa0, 0xb3, 0xde, 0xad,
+                                         0xbe, 0xef}));
+  EXPECT_EQ(ReadProgramBinary(path, Endianness::BIG), words);
+  EXPECT_EQ(ReadProgramBinary(path, Endianness::LITTLE),
+            (std::vector<uint32_t>{0xb3a02900, 0xefbeadde}));
+}
+
+TEST(AssemblerTest, ParseAsmRoundtripsDisassembly) {
+  Program program = {Instr()
+                         .Src(Unit::UNIT_ABS_IMMEDIATE)
+                         .Si(666)
+                         .Dst(Unit::UNIT_REGISTER)
+                         .Di(1),
+                     Instr()
+                         .Src(Unit::UNIT_MEMORY_OPERAND)
+                         .Soperand(123)
+                         .Dst(Unit::UNIT_ALU_LEFT)
+                         .Di(2),
+                     Instr()
+                         .Src(Unit::UNIT_ABS_OPERAND)
+                         .Soperand(0xdeadbeef)
+                         .Dst(Unit::UNIT_MEMORY_OPERAND)
+                         .Doperand(0x1000),
+                     Instr()
+                         .Src(Unit::UNIT_REGISTER_POINTER)
+                         .Si(3)
+                         .Dst(Unit::UNIT_PC_IF)
+                         .Di(1),
+                     Instr()};
+  std::ostringstream text;
+  for (const Instr& instr : program)
+    text << instr << "  ; comment" << std::endl;
+
+  Program parsed;
+  ParseError error;
+  ASSERT_TRUE(ParseAsm(text.str(), &parsed, &error)) << error.message;
+  EXPECT_EQ(parsed.assemble(), program.assemble());
+}
+
+TEST(AssemblerTest, AssembleIntoAppends) {
+  Instr a = Instr()
+                .Src(Unit::UNIT_ABS_OPERAND)
+                .Soperand(666)
+                .Dst(Unit::UNIT_REGISTER)
+                .Di(1);
+  Instr b = Instr().Src(Unit::UNIT_REGISTER).Si(1).Dst(Unit::UNIT_REGISTER);
+  std::vector<uint32_t> buf;
+  a.AssembleInto(&buf);
+  b.AssembleInto(&buf);
+  std::vector<uint32_t> expected = a.assemble();
+  for (uint32_t word : b.assemble())
+    expected.push_back(word);
+  EXPECT_EQ(buf, expected);
+  EXPECT_EQ(buf, Program({a, b}).assemble());
+
+  AssembleError error;
+  EXPECT_FALSE(Instr()
+                   .Src(Unit::UNIT_MEMORY_OPERAND)
+                   .Dst(Unit::UNIT_REGISTER)
+                   .TryAssembleInto(&buf, &error));
+  EXPECT_EQ(buf, expected);
+}
+
+TEST(AssemblerTest, CommentsArentEncoded) {
+  Instr plain = Instr()
+                    .Src(Unit::UNIT_ABS_OPERAND)
+                    .Soperand(666)
+                    .Dst(Unit::UNIT_REGISTER)
+                    .Di(1);
+  Instr commented = plain;
+  commented.Comment("x = 666");
+  EXPECT_EQ(commented.assemble(), plain.assemble());
+  EXPECT_EQ(commented, plain);
+  EXPECT_EQ(std::hash<Instr>()(commented), std::hash<Instr>()(plain));
+
+  std::ostringstream os;
+  os << commented << std::endl << Instr::Nop().Comment("pad");
+  EXPECT_EQ(os.str(), "R01 := #0000029a ; x = 666\nNOP ; pad");
+
+  Program parsed;
+  ParseError error;
+  ASSERT_TRUE(ParseAsm("; header\n" + os.str(), &parsed, &error));
+  ASSERT_EQ(parsed.size(), 2);
+  EXPECT_EQ(parsed.begin()[0].comment(), "x = 666");
+  EXPECT_EQ(parsed.begin()[1].comment(), "pad");
+}
+
+TEST(AssemblerTest, ParseAsmReportsBadLine) {
+  Program program;
+  ParseError error;
+  EXPECT_FALSE(ParseAsm("R00 := #001\n\nR01 := ALU0:RESULT\nR02 = R01\n",
+                        &program, &error));
+  EXPECT_EQ(error.line, 4);
+  EXPECT_EQ(error.text, "R02 = R01");
+
+  EXPECT_FALSE(ParseAsm("#001 := R00", &program, &error));
+  EXPECT_EQ(error.line, 1);
+  EXPECT_EQ(error.message, "UNIT_ABS_IMMEDIATE can't be used as a destination");
+
+  // Register numbers are 12-bit immediates.
+  EXPECT_FALSE(ParseAsm("R1000 := #001", &program, &error));
+}
+
+TEST_F(TTATest, LoadDataAndDumpRange) {
+  Load({Instr()
+            .Src(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Si(100)
+            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Di(110),
+        Instr()
+            .Src(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Si(101)
+            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Di(111)});
+  LoadData({{100, 666}, {101, 777}});
+  LoadDataMap({{102, 888}});
+  Step();  // First clock, still in reset
+  RunUntil(50);
+  EXPECT_EQ(DumpDataRange(110, 112),
+            (std::vector<std::pair<uint32_t, uint32_t>>{{110, 666},
+                                                        {111, 777}}));
+  EXPECT_EQ(DumpDataRange(102, 103),
+            (std::vector<std::pair<uint32_t, uint32_t>>{{102, 888}}));
+}
+
+TEST(AssemblerTest, CyclesForMoves) {
+  Instr reg_to_reg = Instr()
+                         .Src(Unit::UNIT_REGISTER)
+                         .Si(0)
+                         .Dst(Unit::UNIT_REGISTER)
+                         .Di(1);
+  Instr mem_to_reg = Instr()
+                         .Src(Unit::UNIT_MEMORY_IMMEDIATE)
+                         .Si(0)
+                         .Dst(Unit::UNIT_REGISTER)
+                         .Di(1);
+  Instr mem_operand_to_reg = Instr()
+                                 .Src(Unit::UNIT_MEMORY_OPERAND)
+                                 .Soperand(0)
+                                 .Dst(Unit::UNIT_REGISTER)
+                                 .Di(1);
+  EXPECT_LT(CyclesFor(reg_to_reg), CyclesFor(mem_to_reg));
+  EXPECT_LT(CyclesFor(mem_to_reg), CyclesFor(mem_operand_to_reg));
+
+  Program program{reg_to_reg, mem_to_reg, mem_operand_to_reg};
+  EXPECT_EQ(program.TotalCycles(), CyclesFor(reg_to_reg) +
+                                       CyclesFor(mem_to_reg) +
+                                       CyclesFor(mem_operand_to_reg));
+}
+
+TEST(AssemblerTest, InstrAccessors) {
+  Instr instr = Instr()
+                    .Src(Unit::UNIT_MEMORY_OPERAND)
+                    .Soperand(0xdeadbeef)
+                    .Dst(Unit::UNIT_REGISTER)
+                    .Di(7);
+  EXPECT_EQ(instr.src_unit(), Unit::UNIT_MEMORY_OPERAND);
+  EXPECT_EQ(instr.dst_unit(), Unit::UNIT_REGISTER);
+  EXPECT_EQ(instr.si(), 0);
+  EXPECT_EQ(instr.di(), 7);
+  EXPECT_EQ(instr.soperand(), 0xdeadbeef);
+  EXPECT_FALSE(instr.doperand().has_value());
+}
+
+TEST(AssemblerTest, InstrEquality) {
+  Instr a = Instr()
+                .Src(Unit::UNIT_ABS_OPERAND)
+                .Soperand(666)
+                .Dst(Unit::UNIT_REGISTER)
+                .Di(1);
+  Instr b = Instr()
+                .Src(Unit::UNIT_ABS_OPERAND)
+                .Soperand(666)
+                .Dst(Unit::UNIT_REGISTER)
+                .Di(1);
+  EXPECT_EQ(a, b);
+  EXPECT_NE(a, Instr(b).Di(2));
+  EXPECT_NE(Instr().Src(Unit::UNIT_ABS_OPERAND),
+            Instr().Src(Unit::UNIT_ABS_OPERAND).Soperand(0));
+
+  std::vector<uint32_t> words = a.assemble();
+  size_t len;
+  EXPECT_EQ(Instr::Decode(words, 0, &len), a);
+  EXPECT_EQ(len, words.size());
+}
+
+TEST(AssemblerTest, TryDecodeRejectsTruncatedOperand) {
+  std::vector<uint32_t> words = Instr()
+                                    .Src(Unit::UNIT_MEMORY_OPERAND)
+                                    .Soperand(123)
+                                    .Dst(Unit::UNIT_MEMORY_OPERAND)
+                                    .Doperand(124)
+                                    .assemble();
+  ASSERT_EQ(words.size(), 3);
+
+  Instr instr;
+  size_t len;
+  DecodeError error;
+  std::vector<uint32_t> no_doperand(words.begin(), words.end() - 1);
+  EXPECT_FALSE(Instr::TryDecode(no_doperand, 0, &instr, &len, &error));
+  EXPECT_EQ(error.kind, DecodeError::Kind::TRUNCATED_OPERAND);
+  EXPECT_EQ(error.role, Role::DESTINATION);
+
+  std::vector<uint32_t> opcode_only(words.begin(), words.begin() + 1);
+  EXPECT_FALSE(Instr::TryDecode(opcode_only, 0, &instr, &len, &error));
+  EXPECT_EQ(error.unit, Unit::UNIT_MEMORY_OPERAND);
+  EXPECT_EQ(error.role, Role::SOURCE);
+
+  EXPECT_TRUE(Instr::TryDecode(words, 0, &instr, &len, &error));
+  EXPECT_EQ(len, 3);
+}
+
+TEST(AssemblerTest, OpcodeWordLayout) {
+  std::vector<uint32_t> words = Instr()
+                                    .Src(Unit::UNIT_MEMORY_IMMEDIATE)
+                                    .Si(0x123)
+                                    .Dst(Unit::UNIT_REGISTER)
+                                    .Di(0x456)
+                                    .assemble();
+  ASSERT_EQ(words.size(), 1);
+  EXPECT_EQ(words[0], (uint32_t)Unit::UNIT_MEMORY_IMMEDIATE << kSrcUnitShift |
+                          0x123 << kSiShift |
+                          (uint32_t)Unit::UNIT_REGISTER << kDstUnitShift |
+                          0x456 << kDiShift);
+  EXPECT_EQ(words[0], 0x45631238);
+}
+
+TEST_F(TTATest, EveryRegisterHoldsItsValue) {
+  Program program;
+  for (short r = 0; r < kNumRegisters; r++)
+    program.Mov(Unit::UNIT_ABS_IMMEDIATE, 100 + r, Unit::UNIT_REGISTER, r);
+  for (short r = 0; r < kNumRegisters; r++)
+    program.Mov(Unit::UNIT_REGISTER, r, Unit::UNIT_MEMORY_IMMEDIATE, 200 + r);
+  Load(program);
+  Step();  // First clock, still in reset
+  RunUntil(2000);
+  std::vector<std::pair<uint32_t, uint32_t>> expected;
+  for (int r = 0; r < kNumRegisters; r++)
+    expected.emplace_back(200 + r, 100 + r);
+  EXPECT_TRUE(DataMatches(expected));
+}
+
+TEST_F(TTATest, StoreAndLoadViaPointer) {
+  Program program;
+  program.Mov(Unit::UNIT_ABS_IMMEDIATE, 123, Unit::UNIT_REGISTER, 1)
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, 666, Unit::UNIT_REGISTER, 2)
+      .Add(Instr::StoreViaPointer(Unit::UNIT_REGISTER, 2, 1))
+      .Add(Instr::LoadViaPointer(1, Unit::UNIT_REGISTER, 3))
+      .Mov(Unit::UNIT_REGISTER, 3, Unit::UNIT_MEMORY_IMMEDIATE, 124);
+  Load(program);
+  Step();  // First clock, still in reset
+  RunUntil(100);
+  EXPECT_EQ(ram()->mem()[123], 666);
+  EXPECT_EQ(ram()->mem()[124], 666);
+}
+
+TEST(AssemblerTest, InstrLenWords) {
+  for (Instr instr : {Instr(),
+                      Instr()
+                          .Src(Unit::UNIT_ABS_OPERAND)
+                          .Soperand(666)
+                          .Dst(Unit::UNIT_REGISTER),
+                      Instr()
+                          .Src(Unit::UNIT_ABS_OPERAND)
+                          .Soperand(666)
+                          .Dst(Unit::UNIT_MEMORY_OPERAND)
+                          .Doperand(123)}) {
+    EXPECT_EQ(instr.LenWords(), instr.assemble().size()) << instr;
+  }
+}
+
+TEST_F(TTATest, OperandWordsAdvanceFetchAddress) {
+  Load({Instr()
+            .Src(Unit::UNIT_ABS_OPERAND)
+            .Soperand(0x12345678)
+            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Di(100),
+        Instr()
+            .Src(Unit::UNIT_ABS_OPERAND)
+            .Soperand(0x9abcdef0)
+            .Dst(Unit::UNIT_MEMORY_OPERAND)
+            .Doperand(101),
+        Instr()
+            .Src(Unit::UNIT_ABS_IMMEDIATE)
+            .Si(7)
+            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Di(102)});
+  Step();  // First clock, still in reset
+
+  std::vector<uint32_t> fetches;
+  for (int i = 0; i < 100; i++) {
+    StepResult result = Step();
+    if (result.instr_fetch)
+      fetches.push_back(*result.instr_fetch);
+  }
+  ASSERT_GE(fetches.size(), 3);
+  EXPECT_EQ(fetches[0], 0);
+  EXPECT_EQ(fetches[1], 2);
+  EXPECT_EQ(fetches[2], 5);
+  EXPECT_EQ(ram()->mem()[100], 0x12345678);
+  EXPECT_EQ(ram()->mem()[101], 0x9abcdef0);
+  EXPECT_EQ(ram()->mem()[102], 7);
+}
+
+TEST_F(TTATest, MemTraceRecordsAccessesInOrder) {
+  EnableMemTrace();
+  Load({Instr()
+            .Src(Unit::UNIT_ABS_IMMEDIATE)
+            .Si(666)
+            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Di(123),
+        Instr()
+            .Src(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Si(123)
+            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Di(124)});
+  Step();  // First clock, still in reset
+  RunUntil(100);
+
+  const std::vector<MemAccess>& trace = mem_trace();
+  ASSERT_EQ(trace.size(), 3);
+  EXPECT_TRUE(trace[0].is_write);
+  EXPECT_EQ(trace[0].addr, 123);
+  EXPECT_FALSE(trace[1].is_write);
+  EXPECT_EQ(trace[1].addr, 123);
+  EXPECT_EQ(trace[1].value, 666);
+  EXPECT_TRUE(trace[2].is_write);
+  EXPECT_EQ(trace[2].addr, 124);
+  EXPECT_EQ(trace[2].value, 666);
+  EXPECT_LT(trace[0].cycle, trace[1].cycle);
+  EXPECT_LT(trace[1].cycle, trace[2].cycle);
+
+  EXPECT_EQ(MemTraceFor(123).size(), 2);
+  EXPECT_EQ(MemTraceFor(124).size(), 1);
+}
+
+// Each load reports the word it read, not whatever the bus held from the
+// load before.
+TEST_F(TTATest, BackToBackLoadsReportTheirOwnValues) {
+  EnableMemTrace();
+  Load(Program()
+           .Mov(Unit::UNIT_MEMORY_IMMEDIATE, 10, Unit::UNIT_REGISTER, 0)
+           .Mov(Unit::UNIT_MEMORY_IMMEDIATE, 11, Unit::UNIT_REGISTER, 1));
+  LoadData({{10, 111}, {11, 222}});
+  Step();  // First clock, still in reset
+
+  std::vector<std::pair<uint32_t, uint32_t>> reads;
+  for (int i = 0; i < 100; i++) {
+    StepResult result = Step();
+    if (result.data_read)
+      reads.push_back(*result.data_read);
+  }
+  EXPECT_EQ(reads, (std::vector<std::pair<uint32_t, uint32_t>>{{10, 111},
+                                                                {11, 222}}));
+  ASSERT_EQ(mem_trace().size(), 2);
+  EXPECT_EQ(mem_trace()[0].value, 111);
+  EXPECT_EQ(mem_trace()[1].value, 222);
+}
+
+TEST(AssemblerTest, UnitsAndInstrsAreHashable) {
+  std::unordered_set<Unit> units;
+  for (int u = (int)Unit::UNIT_NONE; u <= (int)Unit::UNIT_PC_IF; u++)
+    units.insert((Unit)u);
+  units.insert(Unit::UNIT_NONE);
+  EXPECT_EQ(units.size(), 15);
+
+  std::unordered_set<ALUOp> ops(std::begin(kAllALUOps), std::end(kAllALUOps));
+  EXPECT_EQ(ops.size(), std::size(kAllALUOps));
+
+  std::unordered_map<Instr, int> instrs;
+  Instr a = Instr()
+                .Src(Unit::UNIT_ABS_OPERAND)
+                .Soperand(666)
+                .Dst(Unit::UNIT_REGISTER)
+                .Di(1);
+  instrs[a] = 1;
+  instrs[Instr(a).Soperand(667)] = 2;
+  instrs[Instr(a)] = 3;
+  EXPECT_EQ(instrs.size(), 2);
+  EXPECT_EQ(instrs[a], 3);
+}
+
+TEST(TTAHarnessTest, MachineStateJson) {
+  // R1 up are 0 in both states.
+  std::string high_registers;
+  for (int r = 1; r < kNumRegisters; r++)
+    high_registers += ", 0";
+  EXPECT_EQ(ToJson({3, {}, {}}), "{\n  \"pc\": 3,\n  \"registers\": [0" +
+                                     high_registers +
+                                     "],\n  \"data_mem\": {}\n}\n");
+
+  MachineState state{2, {42}, {{124, 7}, {123, 666}}};
+  EXPECT_EQ(ToJson(state), "{\n"
+                           "  \"pc\": 2,\n"
+                           "  \"registers\": [42" +
+                               high_registers +
+                               "],\n"
+                               "  \"data_mem\": {\n"
+                               "    \"123\": 666,\n"
+                               "    \"124\": 7\n"
+                               "  }\n"
+                               "}\n");
 }
 
-// TODO: set/get PC, stack, other ALU ops
\ No newline at end of file
+TEST_F(TTATest, DumpStateAfterRun) {
+  Load({Instr()
+            .Src(Unit::UNIT_ABS_IMMEDIATE)
+            .Si(666)
+            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Di(123),
+        Instr()
+            .Src(Unit::UNIT_ABS_IMMEDIATE)
+            .Si(7)
+            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Di(124),
+        Instr()
+            .Src(Unit::UNIT_ABS_IMMEDIATE)
+            .Si(42)
+            .Dst(Unit::UNIT_REGISTER)
+            .Di(5)});
+  Step();  // First clock, still in reset
+  RunUntil(50);
+  MachineState state = DumpState();
+  EXPECT_EQ(state.data_mem,
+            (std::map<uint32_t, uint32_t>{{123, 666}, {124, 7}}));
+  EXPECT_EQ(state.registers[5], 42);
+  EXPECT_EQ(state.registers[0], 0);
+  EXPECT_GE(state.pc, 3);
+}
+
+TEST(BusMonitorTest, AcceptsHeldValidAndFlagsViolations) {
+  BusMonitor monitor;
+  // Read 10, then keep valid high and move on to 11 once ready is seen.
+  monitor.Observe(0, {true, false, 10, 0, 0});
+  monitor.Observe(1, {true, false, 10, 0, 0});
+  monitor.Observe(2, {true, true, 11, 0, 0});
+  monitor.Observe(3, {true, true, 11, 0, 0});
+  // A write, held after it completes.
+  monitor.Observe(4, {true, true, 12, 666, 0xf});
+  monitor.Observe(5, {true, true, 12, 666, 0xf});
+  monitor.Observe(6, {false, true, 0, 0, 0});
+  EXPECT_TRUE(monitor.violations().empty());
+
+  monitor.Observe(7, {true, false, 20, 0, 0});
+  monitor.Observe(8, {true, false, 21, 0, 0});
+  monitor.Observe(9, {true, true, 21, 0, 0});
+  monitor.Observe(10, {true, true, 30, 1, 0xf});
+  monitor.Observe(11, {true, false, 30, 2, 0xf});
+  monitor.Observe(12, {false, false, 0, 0, 0});
+  ASSERT_EQ(monitor.violations().size(), 3);
+  EXPECT_EQ(monitor.violations()[0].kind,
+            ProtocolViolation::Kind::ADDR_CHANGED);
+  EXPECT_EQ(monitor.violations()[0].addr, 20);
+  EXPECT_EQ(monitor.violations()[1].kind,
+            ProtocolViolation::Kind::DATA_CHANGED);
+  EXPECT_EQ(monitor.violations()[1].cycle, 11);
+  EXPECT_EQ(monitor.violations()[2].kind,
+            ProtocolViolation::Kind::VALID_DROPPED);
+
+  monitor.Clear();
+  EXPECT_TRUE(monitor.violations().empty());
+}
+
+TEST_F(TTATest, BusHandshakesFollowProtocol) {
+  Load({Instr()
+            .Src(Unit::UNIT_ABS_OPERAND)
+            .Soperand(666)
+            .Dst(Unit::UNIT_MEMORY_OPERAND)
+            .Doperand(123),
+        Instr()
+            .Src(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Si(123)
+            .Dst(Unit::UNIT_REGISTER)
+            .Di(0),
+        Instr()
+            .Src(Unit::UNIT_REGISTER)
+            .Si(0)
+            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Di(124)});
+  Step();  // First clock, still in reset
+  RunUntil(100);
+  EXPECT_EQ(ram()->mem()[124], 666);
+  for (const auto& v : instr_bus_monitor().violations())
+    ADD_FAILURE() << "instruction bus: " << v;
+  for (const auto& v : data_bus_monitor().violations())
+    ADD_FAILURE() << "data bus: " << v;
+}
+
+// Count mem[100] up to 3 with a backward branch, loaded at address 0 and
+// then at 64 behind a jump from 0.
+TEST_F(TTATest, LabeledProgramRelocates) {
+  Program setup;
+  setup.Mov(Unit::UNIT_ABS_IMMEDIATE, 1, Unit::UNIT_ALU_RIGHT, 0)
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, (short)ALUOp::ALU_ADD,
+           Unit::UNIT_ALU_OPERATOR, 0)
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, 3, Unit::UNIT_ALU_RIGHT, 1)
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, (short)ALUOp::ALU_LT,
+           Unit::UNIT_ALU_OPERATOR, 1);
+  Program body;
+  body.Mov(Unit::UNIT_MEMORY_IMMEDIATE, 100, Unit::UNIT_ALU_LEFT, 0)
+      .Mov(Unit::UNIT_ALU_RESULT, 0, Unit::UNIT_MEMORY_IMMEDIATE, 100)
+      .Mov(Unit::UNIT_MEMORY_IMMEDIATE, 100, Unit::UNIT_ALU_LEFT, 1);
+  LabeledProgram loop(setup.begin(), setup.end());
+  loop.push_back(Instr::Label("loop"));
+  loop.insert(loop.end(), body.begin(), body.end());
+  LabeledProgram branch = BranchIf(1, "loop");
+  loop.insert(loop.end(), branch.begin(), branch.end());
+  loop.push_back(Instr()
+                     .Src(Unit::UNIT_ABS_IMMEDIATE)
+                     .Si(666)
+                     .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+                     .Di(101));
+
+  LoadLabeled(loop);
+  Step();  // First clock, still in reset
+  RunUntil(1000);
+  EXPECT_EQ(ram()->mem()[100], 3);
+  EXPECT_EQ(ram()->mem()[101], 666);
+
+  ResetModel();
+  Load({Instr().Src(Unit::UNIT_ABS_IMMEDIATE).Si(64).Dst(Unit::UNIT_PC)});
+  LoadLabeled(loop, 64);
+  RunUntil(1000);
+  EXPECT_EQ(ram()->mem()[100], 3);
+  EXPECT_EQ(ram()->mem()[101], 666);
+}
+
+TEST(AssemblerTest, AssembleLabeledAtBase) {
+  LabeledProgram program = {Instr::Jump("end"), Instr::Label("end")};
+  EXPECT_EQ(AssembleLabeled(program)[1], 2);
+  EXPECT_EQ(AssembleLabeled(program, 64)[1], 66);
+}
+
+// Every commutative op gives the same result with its operands swapped.
+TEST_F(TTATest, CommutativeAluOps) {
+  Program program;
+  program.Mov(Unit::UNIT_ABS_IMMEDIATE, 7, Unit::UNIT_REGISTER, 0)
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, 3, Unit::UNIT_REGISTER, 1);
+  std::vector<ALUOp> ops;
+  for (ALUOp op : kAllALUOps) {
+    if (!IsCommutative(op))
+      continue;
+    short addr = 100 + 2 * ops.size();
+    program.Alu(0, 1, op, 0)
+        .Mov(Unit::UNIT_ALU_RESULT, 0, Unit::UNIT_MEMORY_IMMEDIATE, addr)
+        .Alu(1, 0, op, 0)
+        .Mov(Unit::UNIT_ALU_RESULT, 0, Unit::UNIT_MEMORY_IMMEDIATE, addr + 1);
+    ops.push_back(op);
+  }
+  Load(program);
+  Step();  // First clock, still in reset
+  RunUntil(1000);
+  for (size_t i = 0; i < ops.size(); i++) {
+    EXPECT_EQ(ram()->mem()[100 + 2 * i], ram()->mem()[101 + 2 * i])
+        << ops[i];
+  }
+}
+
+TEST(AssemblerTest, AluOpClassification) {
+  EXPECT_TRUE(IsCommutative(ALUOp::ALU_ADD));
+  EXPECT_FALSE(IsCommutative(ALUOp::ALU_SUB));
+  EXPECT_FALSE(IsCommutative(ALUOp::ALU_LT));
+  EXPECT_TRUE(IsUnary(ALUOp::ALU_NOT));
+  EXPECT_FALSE(IsUnary(ALUOp::ALU_ADD));
+  for (ALUOp op : kAllALUOps)
+    EXPECT_FALSE(IsCommutative(op) && IsUnary(op)) << op;
+}
+
+// 0x12345678 * 0x9abcdef0 = 0x0b00ea4e_242d2080.
+TEST_F(TTATest, AluMultiplyHighWord) {
+  Program program;
+  program
+      .Add(Instr()
+               .Src(Unit::UNIT_ABS_OPERAND)
+               .Soperand(0x12345678)
+               .Dst(Unit::UNIT_REGISTER)
+               .Di(0))
+      .Add(Instr()
+               .Src(Unit::UNIT_ABS_OPERAND)
+               .Soperand(0x9abcdef0)
+               .Dst(Unit::UNIT_REGISTER)
+               .Di(1))
+      .Alu(0, 1, ALUOp::ALU_MUL, 0)
+      .Mov(Unit::UNIT_ALU_RESULT, 0, Unit::UNIT_MEMORY_IMMEDIATE, 100)
+      .Alu(0, 1, ALUOp::ALU_MULHU, 1)
+      .Mov(Unit::UNIT_ALU_RESULT, 1, Unit::UNIT_MEMORY_IMMEDIATE, 101);
+  Load(program);
+  Step();  // First clock, still in reset
+  RunUntil(200);
+  uint64_t product = (uint64_t)0x12345678 * 0x9abcdef0;
+  EXPECT_EQ(ram()->mem()[100], (uint32_t)product);
+  EXPECT_EQ(ram()->mem()[101], (uint32_t)(product >> 32));
+}
+
+TEST(AssemblerTest, ProgramSoARoundtrip) {
+  Program program;
+  program.Mov(Unit::UNIT_ABS_IMMEDIATE, 666, Unit::UNIT_REGISTER, 0)
+      .Alu(0, 1, ALUOp::ALU_ADD, 2)
+      .Add(Instr()
+               .Src(Unit::UNIT_MEMORY_OPERAND)
+               .Soperand(123)
+               .Dst(Unit::UNIT_MEMORY_OPERAND)
+               .Doperand(124));
+  ProgramSoA soa(program);
+  ASSERT_EQ(soa.size(), program.size());
+  EXPECT_EQ(soa.dst_units[1], Unit::UNIT_ALU_LEFT);
+  EXPECT_EQ(soa.dis[1], 2);
+  EXPECT_EQ(soa.soperands[4], 123);
+  EXPECT_FALSE(soa.soperands[0].has_value());
+
+  Program back = soa.ToProgram();
+  EXPECT_TRUE(std::equal(back.begin(), back.end(), program.begin(),
+                         program.end()));
+}
+
+TEST(OptimizerTest, DropsSelfMovesAndDeadStores) {
+  Program program;
+  program.Mov(Unit::UNIT_ABS_IMMEDIATE, 1, Unit::UNIT_REGISTER, 0)
+      .Mov(Unit::UNIT_REGISTER, 0, Unit::UNIT_REGISTER, 0)
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, 2, Unit::UNIT_REGISTER, 0)
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, (short)ALUOp::ALU_ADD,
+           Unit::UNIT_ALU_OPERATOR, 0)
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, (short)ALUOp::ALU_ADD,
+           Unit::UNIT_ALU_OPERATOR, 0)
+      .Mov(Unit::UNIT_REGISTER, 0, Unit::UNIT_ALU_LEFT, 0)
+      .Mov(Unit::UNIT_REGISTER, 0, Unit::UNIT_ALU_RIGHT, 0)
+      .Mov(Unit::UNIT_ALU_RESULT, 0, Unit::UNIT_MEMORY_IMMEDIATE, 100);
+  Program optimized = Optimize(program);
+  EXPECT_EQ(optimized.size(), 5);
+  EXPECT_EQ(*optimized.begin(), Instr()
+                                    .Src(Unit::UNIT_ABS_IMMEDIATE)
+                                    .Si(2)
+                                    .Dst(Unit::UNIT_REGISTER)
+                                    .Di(0));
+}
+
+TEST(OptimizerTest, KeepsStoresThatAreRead) {
+  Program program;
+  // R0 is read by the second move before the third overwrites it.
+  program.Mov(Unit::UNIT_ABS_IMMEDIATE, 1, Unit::UNIT_REGISTER, 0)
+      .Mov(Unit::UNIT_REGISTER, 0, Unit::UNIT_REGISTER, 1)
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, 2, Unit::UNIT_REGISTER, 0)
+      // Reading R0 and writing it back is not dead.
+      .Mov(Unit::UNIT_REGISTER_POINTER, 0, Unit::UNIT_REGISTER, 0)
+      // Memory reads are kept even if overwritten.
+      .Mov(Unit::UNIT_MEMORY_IMMEDIATE, 5, Unit::UNIT_REGISTER, 2)
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, 3, Unit::UNIT_REGISTER, 2);
+  EXPECT_EQ(Optimize(program).size(), program.size());
+
+  Program jumps;
+  jumps.Mov(Unit::UNIT_ABS_IMMEDIATE, 1, Unit::UNIT_REGISTER, 0)
+      .Mov(Unit::UNIT_REGISTER, 0, Unit::UNIT_REGISTER, 0)
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, 0, Unit::UNIT_PC, 0);
+  EXPECT_EQ(Optimize(jumps).size(), jumps.size());
+}
+
+TEST_F(TTATest, OptimizedProgramLeavesSameState) {
+  Program program;
+  program.Mov(Unit::UNIT_ABS_IMMEDIATE, 7, Unit::UNIT_REGISTER, 0)
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, 5, Unit::UNIT_REGISTER, 1)
+      .Mov(Unit::UNIT_REGISTER, 1, Unit::UNIT_REGISTER, 1)
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, 9, Unit::UNIT_ALU_LEFT, 0)
+      .Alu(0, 1, ALUOp::ALU_SUB, 0)
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, (short)ALUOp::ALU_SUB,
+           Unit::UNIT_ALU_OPERATOR, 0)
+      .Mov(Unit::UNIT_ALU_RESULT, 0, Unit::UNIT_MEMORY_IMMEDIATE, 100)
+      .Mov(Unit::UNIT_REGISTER, 0, Unit::UNIT_MEMORY_IMMEDIATE, 101);
+  Program optimized = Optimize(program);
+  ASSERT_LT(optimized.size(), program.size());
+
+  Load(program);
+  Step();  // First clock, still in reset
+  RunUntil(300);
+  std::map<uint32_t, uint32_t> expected = DumpState().data_mem;
+  EXPECT_EQ(expected[100], 2);
+
+  ResetModel();
+  Load(optimized);
+  RunUntil(300);
+  EXPECT_EQ(DumpState().data_mem, expected);
+}
+
+TEST(OptimizerTest, NeverCostsMoreCycles) {
+  Program self_move;
+  self_move.Mov(Unit::UNIT_ABS_IMMEDIATE, 1, Unit::UNIT_REGISTER, 0)
+      .Mov(Unit::UNIT_REGISTER, 0, Unit::UNIT_REGISTER, 0)
+      .Mov(Unit::UNIT_REGISTER, 0, Unit::UNIT_MEMORY_IMMEDIATE, 100);
+  EXPECT_LT(Optimize(self_move).TotalCycles(), self_move.TotalCycles());
+
+  Program nothing_to_do;
+  nothing_to_do.Mov(Unit::UNIT_ABS_IMMEDIATE, 1, Unit::UNIT_REGISTER, 0)
+      .Alu(0, 0, ALUOp::ALU_ADD, 0)
+      .Mov(Unit::UNIT_ALU_RESULT, 0, Unit::UNIT_MEMORY_IMMEDIATE, 100);
+  EXPECT_EQ(Optimize(nothing_to_do).TotalCycles(),
+            nothing_to_do.TotalCycles());
+
+  for (const Program& program : {self_move, nothing_to_do})
+    EXPECT_LE(Optimize(program).TotalCycles(), program.TotalCycles());
+}
+
+TEST_F(TTATest, ReadAluOperatorBack) {
+  Program program;
+  program
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, (short)ALUOp::ALU_MUL,
+           Unit::UNIT_ALU_OPERATOR, 3)
+      .Add(Instr::ReadAluOp(3, Unit::UNIT_MEMORY_IMMEDIATE, 100))
+      .Add(Instr::ReadAluOp(4, Unit::UNIT_MEMORY_IMMEDIATE, 101));
+  Load(program);
+  Step();  // First clock, still in reset
+  ram()->mem()[101] = 666;
+  RunUntil(100);
+  EXPECT_EQ(ram()->mem()[100], (uint32_t)ALUOp::ALU_MUL);
+  EXPECT_EQ(ram()->mem()[101], (uint32_t)ALUOp::ALU_NOP);
+}
+
+TEST_F(TTATest, DebuggerBreakpointsAndStepping) {
+  Program program;
+  for (short i = 0; i < 4; i++)
+    program.Mov(Unit::UNIT_ABS_IMMEDIATE, i + 1, Unit::UNIT_MEMORY_IMMEDIATE,
+                100 + i);
+  Load(program);
+  Step();  // First clock, still in reset
+
+  Debugger debugger(this);
+  std::optional<MachineState> state = debugger.StepInstruction();
+  ASSERT_TRUE(state);
+  EXPECT_EQ(state->data_mem,
+            (std::map<uint32_t, uint32_t>{{100, 1}}));
+
+  debugger.SetBreakpoint(3);
+  state = debugger.ContinueUntilBreak();
+  ASSERT_TRUE(state);
+  // The move at 2 has completed by the time 3 is fetched.
+  EXPECT_EQ(state->data_mem,
+            (std::map<uint32_t, uint32_t>{{100, 1}, {101, 2}, {102, 3}}));
+
+  debugger.ClearBreakpoint(3);
+  state = debugger.StepInstruction();
+  ASSERT_TRUE(state);
+  EXPECT_EQ(state->data_mem.at(103), 4);
+  EXPECT_FALSE(debugger.ContinueUntilBreak(100));
+}
+
+TEST_F(TTATest, TryLoadRejectsOversizedProgram) {
+  Program program;
+  for (int i = 0; i < 512; i++) {
+    program.Add(Instr()
+                    .Src(Unit::UNIT_ABS_OPERAND)
+                    .Soperand(i)
+                    .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+                    .Di(100));
+  }
+  EXPECT_TRUE(program.FitsIn(1024));
+  EXPECT_FALSE(program.FitsIn(1023));
+
+  ProgramTooLarge error;
+  EXPECT_TRUE(TryLoad(program, 0, &error));
+  EXPECT_FALSE(TryLoad(program, 1, &error));
+  EXPECT_EQ(error.needed, 1024);
+  EXPECT_EQ(error.capacity, 1023);
+  EXPECT_EQ(clk().cycles(), 0);
+}
+
+TEST_F(TTATest, AluComparisonsAreUnsigned) {
+  Program program;
+  program
+      .Add(Instr()
+               .Src(Unit::UNIT_ABS_OPERAND)
+               .Soperand(0xffffffff)
+               .Dst(Unit::UNIT_REGISTER)
+               .Di(0))
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, 1, Unit::UNIT_REGISTER, 1)
+      .Alu(0, 1, ALUOp::ALU_LT, 0)
+      .Mov(Unit::UNIT_ALU_RESULT, 0, Unit::UNIT_MEMORY_IMMEDIATE, 100)
+      .Alu(0, 1, ALUOp::ALU_GT, 1)
+      .Mov(Unit::UNIT_ALU_RESULT, 1, Unit::UNIT_MEMORY_IMMEDIATE, 101);
+  Load(program);
+  Step();  // First clock, still in reset
+  ram()->mem()[100] = 666;
+  RunUntil(200);
+  EXPECT_EQ(ram()->mem()[100], 0);
+  EXPECT_EQ(ram()->mem()[101], 1);
+}
+
+TEST_F(TTATest, DataMatchesReportsEveryMismatch) {
+  LoadData({{100, 1}, {101, 2}, {102, 3}});
+  EXPECT_TRUE(DataMatches({{100, 1}, {101, 2}, {102, 3}}));
+
+  std::vector<MemoryMismatch> mismatches =
+      CompareData({{100, 1}, {101, 5}, {102, 6}});
+  ASSERT_EQ(mismatches.size(), 2);
+  EXPECT_EQ(mismatches[0].addr, 101);
+  EXPECT_EQ(mismatches[0].expected, 5);
+  EXPECT_EQ(mismatches[0].actual, 2);
+  EXPECT_EQ(mismatches[1].addr, 102);
+  EXPECT_FALSE(DataMatches({{101, 5}, {102, 6}}));
+}
+
+TEST_F(TTATest, RunProgramRunsToTheEnd) {
+  Program program = Program()
+                        .Mov(Unit::UNIT_ABS_IMMEDIATE, 666,
+                             Unit::UNIT_REGISTER, 0)
+                        .Mov(Unit::UNIT_REGISTER, 0,
+                             Unit::UNIT_MEMORY_IMMEDIATE, 123);
+  std::optional<int> cycles = RunProgram(program, 100);
+  ASSERT_TRUE(cycles.has_value());
+  EXPECT_LE(*cycles, 8);
+  EXPECT_EQ(instructions_retired(), 2);
+  EXPECT_EQ(ram()->mem()[123], 666);
+
+  // A jump back to itself never reaches the end.
+  EXPECT_FALSE(
+      RunProgram(Program().Mov(Unit::UNIT_ABS_IMMEDIATE, 0, Unit::UNIT_PC, 0),
+                 200)
+          .has_value());
+}
+
+TEST_F(TTATest, ReadPcGivesNextInstructionAddress) {
+  Program program = Program()
+                        .Mov(Unit::UNIT_ABS_IMMEDIATE, 1,
+                             Unit::UNIT_MEMORY_IMMEDIATE, 100)
+                        .Add(Instr()
+                                 .Src(Unit::UNIT_ABS_OPERAND)
+                                 .Soperand(5)
+                                 .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+                                 .Di(101))
+                        .Add(Instr::ReadPc(Unit::UNIT_REGISTER, 0))
+                        .Mov(Unit::UNIT_REGISTER, 0,
+                             Unit::UNIT_MEMORY_IMMEDIATE, 102)
+                        .Add(Instr::ReadPc(Unit::UNIT_MEMORY_IMMEDIATE, 103));
+  ASSERT_TRUE(RunProgram(program, 100).has_value());
+  // The move at 3 reads 4, and the one at 5 reads 6.
+  EXPECT_TRUE(DataMatches({{100, 1}, {101, 5}, {102, 4}, {103, 6}}));
+}
+
+TEST_F(TTATest, StartsAtResetVector) {
+  SetResetVector(100);
+  // Whatever sits at 0 must not run.
+  Load(Program().Mov(Unit::UNIT_ABS_IMMEDIATE, 1,
+                     Unit::UNIT_MEMORY_IMMEDIATE, 123));
+  Load(Program().Mov(Unit::UNIT_ABS_IMMEDIATE, 666,
+                     Unit::UNIT_MEMORY_IMMEDIATE, 124),
+       100);
+  RunUntil(25);
+  EXPECT_EQ(ram()->mem()[123], 0);
+  EXPECT_EQ(ram()->mem()[124], 666);
+}
+
+TEST_F(TTATest, NopsRetireWithoutSideEffects) {
+  Program program;
+  for (int i = 0; i < 10; i++)
+    program.Add(Instr::Nop());
+  std::optional<int> cycles = RunProgram(program, 200);
+  ASSERT_TRUE(cycles.has_value());
+  EXPECT_EQ(instructions_retired(), 10);
+  EXPECT_LE(*cycles, program.TotalCycles());
+  EXPECT_TRUE(DumpState().data_mem.empty());
+}
+
+// UNIT_NONE reads as 0 and swallows what's written to it.
+TEST_F(TTATest, UnitNoneReadsZeroAndDiscards) {
+  EnableMemTrace();
+  Program program =
+      Program()
+          .Mov(Unit::UNIT_ABS_IMMEDIATE, 666, Unit::UNIT_REGISTER, 0)
+          .Mov(Unit::UNIT_NONE, 0, Unit::UNIT_REGISTER, 0)
+          .Mov(Unit::UNIT_ABS_IMMEDIATE, 777, Unit::UNIT_NONE, 0)
+          .Mov(Unit::UNIT_REGISTER, 0, Unit::UNIT_MEMORY_IMMEDIATE, 10);
+  ASSERT_TRUE(RunProgram(program, 100).has_value());
+  EXPECT_EQ(instructions_retired(), 4);
+
+  ASSERT_EQ(mem_trace().size(), 1);
+  EXPECT_EQ(mem_trace()[0].addr, 10);
+  EXPECT_EQ(mem_trace()[0].value, 0);
+  EXPECT_TRUE(mem_trace()[0].is_write);
+}
+
+// Immediate memory addresses reach 4095, but data memory ends at 1023:
+// the last word works and anything above is unmapped.
+TEST_F(TTATest, TopOfDataMemoryByImmediate) {
+  Program program =
+      Program()
+          .Mov(Unit::UNIT_ABS_IMMEDIATE, 666, Unit::UNIT_MEMORY_IMMEDIATE, 1023)
+          .Mov(Unit::UNIT_MEMORY_IMMEDIATE, 1023, Unit::UNIT_MEMORY_IMMEDIATE, 0)
+          .Mov(Unit::UNIT_ABS_IMMEDIATE, 777, Unit::UNIT_MEMORY_IMMEDIATE,
+               kDiMask);
+  ASSERT_EQ(ram()->mem().size(), 1024);
+  ASSERT_TRUE(RunProgram(program, 100).has_value());
+  EXPECT_EQ(ram()->mem()[1023], 666);
+  EXPECT_EQ(ram()->mem()[0], 666);
+  EXPECT_EQ(ram()->unmapped(), std::vector<IData>{kDiMask});
+}
+
+TEST_F(TTATest, MemoryMovesReadBeforeWriting) {
+  Program program =
+      Program()
+          .Mov(Unit::UNIT_MEMORY_IMMEDIATE, 10, Unit::UNIT_MEMORY_IMMEDIATE, 10)
+          .Mov(Unit::UNIT_MEMORY_IMMEDIATE, 10, Unit::UNIT_MEMORY_IMMEDIATE, 11)
+          .Mov(Unit::UNIT_MEMORY_IMMEDIATE, 11, Unit::UNIT_MEMORY_IMMEDIATE, 12)
+          .Mov(Unit::UNIT_ABS_IMMEDIATE, 12, Unit::UNIT_REGISTER, 0)
+          .Mov(Unit::UNIT_REGISTER_POINTER, 0, Unit::UNIT_REGISTER_POINTER, 0);
+  Load(program);
+  LoadData({{10, 666}, {11, 1}, {12, 2}});
+  Step();  // First clock, still in reset
+  RunUntil(100);
+
+  // The self-copies leave their words alone, and the chain carries 666
+  // from 10 through 11 to 12.
+  EXPECT_EQ(ram()->mem()[10], 666);
+  EXPECT_EQ(ram()->mem()[11], 666);
+  EXPECT_EQ(ram()->mem()[12], 666);
+}
+
+// Jump through a two-entry table in data memory. Entry 0 is the move at
+// word 3, entry 1 the one at word 4; falling into entry 0 runs both.
+TEST_F(TTATest, JumpThroughRegister) {
+  for (short entry = 0; entry < 2; entry++) {
+    ResetModel();
+    Load(Program()
+             .Mov(Unit::UNIT_MEMORY_IMMEDIATE, 50 + entry, Unit::UNIT_REGISTER,
+                  0)
+             .Add(Instr::JumpReg(0))
+             .Mov(Unit::UNIT_ABS_IMMEDIATE, 1, Unit::UNIT_MEMORY_IMMEDIATE, 9)
+             .Mov(Unit::UNIT_ABS_IMMEDIATE, 1, Unit::UNIT_MEMORY_IMMEDIATE, 10)
+             .Mov(Unit::UNIT_ABS_IMMEDIATE, 1, Unit::UNIT_MEMORY_IMMEDIATE,
+                  11));
+    LoadData({{50, 3}, {51, 4}});
+    Step();  // First clock, still in reset
+    RunUntil(60);
+
+    EXPECT_EQ(ram()->mem()[9], 0) << "entry " << entry;
+    EXPECT_EQ(ram()->mem()[10], entry == 0 ? 1 : 0) << "entry " << entry;
+    EXPECT_EQ(ram()->mem()[11], 1) << "entry " << entry;
+  }
+}
+
+TEST(AssemblerTest, NopIsTheZeroWord) {
+  EXPECT_EQ(Instr::Nop().assemble(), std::vector<uint32_t>{0});
+  // A bubble still passes through the destination state, like any move.
+  EXPECT_EQ(CyclesFor(Instr::Nop()),
+            CyclesFor(Instr()
+                          .Src(Unit::UNIT_REGISTER)
+                          .Dst(Unit::UNIT_REGISTER)
+                          .Di(1)));
+}
+
+TEST_F(TTATest, CycleHistogramByDestination) {
+  Program program =
+      Program()
+          .Mov(Unit::UNIT_ABS_IMMEDIATE, 1, Unit::UNIT_REGISTER, 0)
+          .Add(Instr()
+                   .Src(Unit::UNIT_MEMORY_OPERAND)
+                   .Soperand(100)
+                   .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+                   .Di(101));
+  std::optional<int> cycles = RunProgram(program, 100);
+  ASSERT_TRUE(cycles.has_value());
+
+  const std::map<Unit, int>& histogram = cycle_histogram();
+  ASSERT_EQ(histogram.count(Unit::UNIT_REGISTER), 1);
+  ASSERT_EQ(histogram.count(Unit::UNIT_MEMORY_IMMEDIATE), 1);
+  EXPECT_GT(histogram.at(Unit::UNIT_MEMORY_IMMEDIATE),
+            histogram.at(Unit::UNIT_REGISTER));
+  EXPECT_EQ(histogram.count(Unit::UNIT_ALU_LEFT), 0);
+
+  int total = 0;
+  for (const auto& [unit, n] : histogram)
+    total += n;
+  EXPECT_LE(total, *cycles);
+
+  ResetModel();
+  EXPECT_TRUE(cycle_histogram().empty());
+}
+
+TEST_F(TTATest, RunsProgramReadFromBinary) {
+  Program program =
+      Program()
+          .Mov(Unit::UNIT_ABS_IMMEDIATE, 666, Unit::UNIT_REGISTER, 0)
+          .Add(Instr()
+                   .Src(Unit::UNIT_REGISTER)
+                   .Si(0)
+                   .Dst(Unit::UNIT_MEMORY_OPERAND)
+                   .Doperand(300));
+  Load(program);
+  Step();  // First clock, still in reset
+  RunUntil(50);
+  std::vector<uint32_t> in_memory = ram()->mem();
+  ASSERT_EQ(in_memory[300], 666);
+
+  for (Endianness order : {Endianness::LITTLE, Endianness::BIG}) {
+    const std::string path = "TTATest-RunsProgramReadFromBinary.bin";
+    ASSERT_TRUE(WriteProgramBinary(path, program.assemble(), order));
+    std::optional<std::vector<uint32_t>> words =
+        ReadProgramBinary(path, order);
+    ASSERT_TRUE(words.has_value());
+    ResetModel();
+    Load(*words);
+    RunUntil(50);
+    EXPECT_EQ(ram()->mem(), in_memory);
+  }
+}
+
+TEST(AssemblerTest, TryAssembleNeverFails) {
+  // Arbitrary moves, including illegal units and out of range immediates,
+  // must either assemble and decode back to themselves or be rejected with
+  // the matching error; never a CHECK failure.
+  std::mt19937 rng(2089);
+  for (int i = 0; i < 10000; i++) {
+    Unit src = (Unit)(rng() % 16);
+    Unit dst = (Unit)(rng() % 16);
+    int si = rng() % 0x10000;
+    int di = rng() % 0x10000;
+    std::optional<uint32_t> soperand, doperand;
+    if (rng() % 2)
+      soperand = rng();
+    if (rng() % 2)
+      doperand = rng();
+
+    Instr instr = Instr().Src(src).Dst(dst);
+    EXPECT_EQ(instr.TrySi(si), si <= (int)kSiMask) << si;
+    EXPECT_EQ(instr.TryDi(di), di <= (int)kDiMask) << di;
+    // The operand setters only accept units that take one.
+    if (soperand && instr.UsesSoperand())
+      instr.Soperand(*soperand);
+    if (doperand && instr.UsesDoperand())
+      instr.Doperand(*doperand);
+
+    std::vector<uint32_t> words;
+    AssembleError error;
+    if (instr.TryAssemble(&words, &error)) {
+      EXPECT_TRUE(IsValidSource(src) && IsValidDest(dst));
+      EXPECT_EQ(words.size(), instr.LenWords());
+      size_t len;
+      EXPECT_EQ(Instr::Decode(words, 0, &len), instr);
+    } else if (!IsValidSource(src) || !IsValidDest(dst)) {
+      EXPECT_EQ(error.kind, AssembleError::Kind::ILLEGAL_DIRECTION);
+    } else {
+      EXPECT_EQ(error.kind, AssembleError::Kind::MISSING_OPERAND);
+      EXPECT_TRUE((instr.UsesSoperand() && !soperand) ||
+                  (instr.UsesDoperand() && !doperand));
+    }
+  }
+}
+
+TEST_F(TTATest, ReadAluInputsBack) {
+  Program program =
+      Program()
+          .Mov(Unit::UNIT_ABS_IMMEDIATE, 42, Unit::UNIT_ALU_LEFT, 0)
+          .Mov(Unit::UNIT_ABS_IMMEDIATE, 7, Unit::UNIT_ALU_RIGHT, 0)
+          .Mov(Unit::UNIT_ABS_IMMEDIATE, 9, Unit::UNIT_ALU_LEFT, 5)
+          .Mov(Unit::UNIT_ALU_LEFT, 0, Unit::UNIT_MEMORY_IMMEDIATE, 100)
+          .Mov(Unit::UNIT_ALU_RIGHT, 0, Unit::UNIT_MEMORY_IMMEDIATE, 101)
+          .Mov(Unit::UNIT_ALU_LEFT, 5, Unit::UNIT_MEMORY_IMMEDIATE, 102);
+  ASSERT_TRUE(RunProgram(program, 200).has_value());
+  EXPECT_TRUE(DataMatches({{100, 42}, {101, 7}, {102, 9}}));
+}
+
+TEST_F(TTATest, CycleCounterDevice) {
+  CycleCounterBackend counter(clk());
+  ram()->AddRegion("cycles", 0x200, 1, &counter);
+  Program program =
+      Program()
+          .Mov(Unit::UNIT_MEMORY_IMMEDIATE, 0x200, Unit::UNIT_MEMORY_IMMEDIATE,
+               100)
+          .Add(Instr::Nop())
+          .Add(Instr::Nop())
+          .Mov(Unit::UNIT_ABS_IMMEDIATE, 1, Unit::UNIT_MEMORY_IMMEDIATE, 0x200)
+          .Mov(Unit::UNIT_MEMORY_IMMEDIATE, 0x200, Unit::UNIT_MEMORY_IMMEDIATE,
+               101);
+  ASSERT_TRUE(RunProgram(program, 200).has_value());
+  EXPECT_GT(ram()->mem()[100], 0);
+  EXPECT_GT(ram()->mem()[101], ram()->mem()[100]);
+}
+
+TEST_F(TTATest, UARTDeviceCollectsOutput) {
+  UARTBackend uart;
+  ram()->AddRegion("uart", 0x300, 1, &uart);
+  Program program;
+  for (char c : std::string("HI"))
+    program.Mov(Unit::UNIT_ABS_IMMEDIATE, c, Unit::UNIT_MEMORY_IMMEDIATE, 0x300);
+  ASSERT_TRUE(RunProgram(program, 200).has_value());
+  EXPECT_EQ(uart.output(), "HI");
+}
+
+TEST(ValidatorTest, FlagsMovesThatWontDoWhatTheySay) {
+  Program program;
+  program.Mov(Unit::UNIT_ABS_IMMEDIATE, (short)ALUOp::ALU_ADD,
+              Unit::UNIT_ALU_OPERATOR, 0)
+      .Mov(Unit::UNIT_ABS_IMMEDIATE, 0x1f, Unit::UNIT_ALU_OPERATOR, 1)
+      .Add(Instr()
+               .Src(Unit::UNIT_ABS_OPERAND)
+               .Soperand(0x100)
+               .Dst(Unit::UNIT_ALU_OPERATOR)
+               .Di(2))
+      .Mov(Unit::UNIT_REGISTER, kNumRegisters, Unit::UNIT_ALU_LEFT, kNumAlus)
+      .Mov(Unit::UNIT_STACK_PUSH_POP, 0, Unit::UNIT_REGISTER, 0)
+      .Mov(Unit::UNIT_REGISTER, kNumRegisters - 1, Unit::UNIT_ALU_RIGHT,
+           kNumAlus - 1);
+
+  std::vector<ProgramWarning> warnings = ValidateProgram(program);
+  ASSERT_EQ(warnings.size(), 5);
+  EXPECT_EQ(warnings[0].kind, ProgramWarning::Kind::UNKNOWN_ALU_OP);
+  EXPECT_EQ(warnings[0].index, 1);
+  EXPECT_EQ(warnings[1].kind, ProgramWarning::Kind::UNKNOWN_ALU_OP);
+  EXPECT_EQ(warnings[1].index, 2);
+  EXPECT_EQ(warnings[2].kind, ProgramWarning::Kind::INDEX_OUT_OF_RANGE);
+  EXPECT_EQ(warnings[2].unit, Unit::UNIT_REGISTER);
+  EXPECT_EQ(warnings[2].role, Role::SOURCE);
+  EXPECT_EQ(warnings[3].kind, ProgramWarning::Kind::INDEX_OUT_OF_RANGE);
+  EXPECT_EQ(warnings[3].unit, Unit::UNIT_ALU_LEFT);
+  EXPECT_EQ(warnings[3].index, 3);
+  EXPECT_EQ(warnings[4].kind, ProgramWarning::Kind::UNIMPLEMENTED_UNIT);
+  EXPECT_EQ(warnings[4].index, 4);
+
+  std::ostringstream os;
+  os << warnings[2];
+  EXPECT_EQ(os.str(), "move 3: source UNIT_REGISTER index is out of range");
+}
+
+TEST(ValidatorTest, FlagsReadsFromNothing) {
+  Program program = Program()
+                        .Add(Instr::Nop())
+                        .Mov(Unit::UNIT_REGISTER, 0, Unit::UNIT_NONE, 0)
+                        .Mov(Unit::UNIT_NONE, 0, Unit::UNIT_REGISTER, 1);
+  std::vector<ProgramWarning> warnings = ValidateProgram(program);
+  ASSERT_EQ(warnings.size(), 1);
+  EXPECT_EQ(warnings[0].kind, ProgramWarning::Kind::READS_NOTHING);
+  EXPECT_EQ(warnings[0].index, 2);
+
+  std::ostringstream os;
+  os << warnings[0];
+  EXPECT_EQ(os.str(), "move 2: moves 0 from UNIT_NONE into UNIT_REGISTER");
+}
+
+TEST(AssemblerTest, AssembleWithMap) {
+  Program program =
+      Program()
+          .Mov(Unit::UNIT_ABS_IMMEDIATE, 1, Unit::UNIT_REGISTER, 0)
+          .Add(Instr()
+                   .Src(Unit::UNIT_ABS_OPERAND)
+                   .Soperand(2)
+                   .Dst(Unit::UNIT_MEMORY_OPERAND)
+                   .Doperand(3))
+          .Add(Instr()
+                   .Src(Unit::UNIT_REGISTER)
+                   .Dst(Unit::UNIT_MEMORY_OPERAND)
+                   .Doperand(4))
+          .Mov(Unit::UNIT_REGISTER, 0, Unit::UNIT_REGISTER, 1);
+  std::vector<size_t> offsets;
+  EXPECT_EQ(program.AssembleWithMap(&offsets), program.assemble());
+  EXPECT_EQ(offsets, (std::vector<size_t>{0, 1, 4, 6}));
+
+  const std::vector<size_t> expected_index = {0, 1, 1, 1, 2, 2, 3};
+  for (uint32_t word = 0; word < expected_index.size(); word++)
+    EXPECT_EQ(InstrIndexAt(program, offsets, word), expected_index[word]);
+  EXPECT_FALSE(InstrIndexAt(program, offsets, 7).has_value());
+}
+
+TEST(AssemblerTest, ImmediateBoundaries) {
+  for (int value : {0, 1, (int)kSiMask - 1, (int)kSiMask}) {
+    Instr instr =
+        Instr().Src(Unit::UNIT_REGISTER_POINTER).Dst(Unit::UNIT_PC_IF);
+    ASSERT_TRUE(instr.TrySi(value)) << value;
+    ASSERT_TRUE(instr.TryDi(value)) << value;
+    size_t len;
+    Instr decoded = Instr::Decode(instr.assemble(), 0, &len);
+    // A full-width immediate mustn't spill into the neighbouring unit field.
+    EXPECT_EQ(decoded.src_unit(), Unit::UNIT_REGISTER_POINTER) << value;
+    EXPECT_EQ(decoded.dst_unit(), Unit::UNIT_PC_IF) << value;
+    EXPECT_EQ(decoded.si(), value);
+    EXPECT_EQ(decoded.di(), value);
+  }
+  EXPECT_EQ(kSiMask, 4095);
+  EXPECT_EQ(kDiMask, 4095);
+
+  // Rejected values leave the field as it was.
+  Instr instr = Instr().Si(7).Di(8);
+  for (int value : {-1, (int)kSiMask + 1, 0x10000}) {
+    EXPECT_FALSE(instr.TrySi(value)) << value;
+    EXPECT_FALSE(instr.TryDi(value)) << value;
+  }
+  EXPECT_EQ(instr.si(), 7);
+  EXPECT_EQ(instr.di(), 8);
+}
+
+TEST(AssemblerTest, DiffPrograms) {
+  Program a = Program()
+                  .Mov(Unit::UNIT_ABS_IMMEDIATE, 1, Unit::UNIT_REGISTER, 1)
+                  .Mov(Unit::UNIT_REGISTER, 1, Unit::UNIT_REGISTER, 2)
+                  .Mov(Unit::UNIT_REGISTER, 2, Unit::UNIT_MEMORY_IMMEDIATE, 3);
+  Program b = Program()
+                  .Mov(Unit::UNIT_ABS_IMMEDIATE, 1, Unit::UNIT_REGISTER, 1)
+                  .Mov(Unit::UNIT_REGISTER, 1, Unit::UNIT_REGISTER, 3);
+  EXPECT_TRUE(DiffPrograms(a, a).empty());
+
+  std::vector<ProgramDifference> differences = DiffPrograms(a, b);
+  ASSERT_EQ(differences.size(), 2);
+  EXPECT_EQ(differences[0].index, 1);
+  EXPECT_EQ(differences[1].index, 2);
+  EXPECT_FALSE(differences[1].b.has_value());
+
+  std::ostringstream os;
+  os << differences[0] << std::endl << differences[1];
+  EXPECT_EQ(os.str(),
+            "1: R02 := R01 -> R03 := R01\n2: *(003) := R02 -> (none)");
+}
+
+TEST(AssemblerTest, MoveRoundtripsThroughInstr) {
+  Program program = {
+      Instr()
+          .Src(Unit::UNIT_ABS_OPERAND)
+          .Soperand(0x1234)
+          .Dst(Unit::UNIT_MEMORY_OPERAND)
+          .Doperand(0x100),
+      Instr()
+          .Src(Unit::UNIT_REGISTER_POINTER)
+          .Si(2)
+          .Dst(Unit::UNIT_ALU_RIGHT)
+          .Di(1),
+      Instr().Src(Unit::UNIT_ALU_RESULT).Si(1).Dst(Unit::UNIT_PC_IF).Di(1),
+      Instr().Src(Unit::UNIT_PC).Dst(Unit::UNIT_REGISTER).Di(3)};
+  for (const Instr& instr : program) {
+    std::optional<Move> move = Move::FromInstr(instr);
+    ASSERT_TRUE(move.has_value()) << instr;
+    EXPECT_EQ(move->ToInstr(), instr);
+  }
+
+  Move load{Move::MemoryImmediate{5},
+            Move::AluInput{0, Move::AluInput::Port::LEFT}};
+  EXPECT_EQ(load.ToInstr(),
+            Instr()
+                .Src(Unit::UNIT_MEMORY_IMMEDIATE)
+                .Si(5)
+                .Dst(Unit::UNIT_ALU_LEFT));
+
+  // Writing an absolute value or a PC_IF read has no Move.
+  EXPECT_FALSE(Move::FromInstr(Instr()
+                                   .Src(Unit::UNIT_REGISTER)
+                                   .Dst(Unit::UNIT_ABS_IMMEDIATE))
+                   .has_value());
+  EXPECT_FALSE(
+      Move::FromInstr(Instr().Src(Unit::UNIT_PC_IF).Dst(Unit::UNIT_REGISTER))
+          .has_value());
+  // Nor does an immediate the unit would ignore.
+  EXPECT_FALSE(Move::FromInstr(
+                   Instr().Src(Unit::UNIT_PC).Si(5).Dst(Unit::UNIT_REGISTER))
+                   .has_value());
+  EXPECT_FALSE(Move::FromInstr(
+                   Instr().Src(Unit::UNIT_REGISTER).Dst(Unit::UNIT_NONE).Di(2))
+                   .has_value());
+  // Nor does an operand unit without its operand.
+  EXPECT_FALSE(Move::FromInstr(Instr()
+                                   .Src(Unit::UNIT_ABS_OPERAND)
+                                   .Dst(Unit::UNIT_REGISTER))
+                   .has_value());
+}
+
+// Instruction memory that remembers which words were fetched.
+class FetchCountingBackend : public RAMBackend {
+ public:
+  using RAMBackend::RAMBackend;
+
+  uint32_t Read(uint32_t addr) override {
+    fetched.insert(addr);
+    return RAMBackend::Read(addr);
+  }
+
+  std::set<uint32_t> fetched;
+};
+
+TEST_F(TTATest, LoadIntoInstructionDevice) {
+  Program program =
+      Program()
+          .Mov(Unit::UNIT_ABS_IMMEDIATE, 666, Unit::UNIT_REGISTER, 0)
+          .Add(Instr()
+                   .Src(Unit::UNIT_REGISTER)
+                   .Si(0)
+                   .Dst(Unit::UNIT_MEMORY_OPERAND)
+                   .Doperand(300));
+  FetchCountingBackend rom(program.LenWords());
+  LoadInto(program, &rom, 0);
+  EXPECT_EQ(rom.mem(), program.assemble());
+
+  prg()->AddRegion("rom", 0, program.LenWords(), &rom);
+  Step();  // First clock, still in reset
+  RunUntil(50);
+  EXPECT_EQ(ram()->mem()[300], 666);
+  EXPECT_EQ(rom.fetched, (std::set<uint32_t>{0, 1, 2}));
+}
+
+TEST_F(TTATest, InstructionFetchHook) {
+  // An endless program, made up as it's fetched: the move at `addr` stores
+  // addr to data memory at addr.
+  SetInstructionFetchHook([](uint32_t addr) {
+    return Instr()
+        .Src(Unit::UNIT_ABS_IMMEDIATE)
+        .Si(addr)
+        .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+        .Di(addr)
+        .assemble()[0];
+  });
+  Step();  // First clock, still in reset
+  RunUntil(200);
+
+  ASSERT_GE(instructions_retired(), 10);
+  for (uint32_t addr = 1; addr < 10; addr++)
+    EXPECT_EQ(ram()->mem()[addr], addr);
+}
+
+TEST_F(TTATest, ResetModelDropsFetchHook) {
+  int hook_calls = 0;
+  SetInstructionFetchHook([&hook_calls](uint32_t addr) {
+    hook_calls++;
+    return Instr::Nop().assemble()[0];
+  });
+  ResetModel();
+  Load(Program().Mov(Unit::UNIT_ABS_IMMEDIATE, 666,
+                     Unit::UNIT_MEMORY_IMMEDIATE, 123));
+  Step();  // First clock, still in reset
+  RunUntil(20);
+
+  EXPECT_EQ(ram()->mem()[123], 666);
+  EXPECT_EQ(hook_calls, 0);
+}
+
+TEST_F(TTATest, GoldenPortTraceReplay) {
+  const std::string path = "TTATest-GoldenPortTraceReplay.ports";
+  auto program = [](short value) {
+    return Program()
+        .Mov(Unit::UNIT_ABS_IMMEDIATE, value, Unit::UNIT_REGISTER, 0)
+        .Mov(Unit::UNIT_REGISTER, 0, Unit::UNIT_MEMORY_IMMEDIATE, 123);
+  };
+
+  ResetModel();
+  Load(program(666));
+  StartPortRecording(path);
+  RunUntil(40);
+  StopPortRecording();
+
+  ResetModel();
+  Load(program(666));
+  EXPECT_FALSE(ReplayAndCompare(path).has_value());
+
+  ResetModel();
+  Load(program(555));
+  std::optional<PortMismatch> mismatch = ReplayAndCompare(path);
+  ASSERT_TRUE(mismatch.has_value());
+  EXPECT_EQ(mismatch->port, "data_data_write_o");
+  EXPECT_EQ(mismatch->expected, 666);
+  EXPECT_EQ(mismatch->actual, 555);
+}
+
+TEST_F(TTATest, DataBusIsWordAddressed) {
+  // Adjacent addresses are whole, separate words rather than overlapping
+  // bytes.
+  Program program;
+  for (int i = 0; i < 4; i++) {
+    program.Add(Instr()
+                    .Src(Unit::UNIT_ABS_OPERAND)
+                    .Soperand(0x11111111 * (i + 1))
+                    .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+                    .Di(100 + i));
+  }
+  program.Mov(Unit::UNIT_MEMORY_IMMEDIATE, 101, Unit::UNIT_MEMORY_IMMEDIATE,
+              200);
+  ASSERT_TRUE(RunProgram(program, 200).has_value());
+  EXPECT_TRUE(DataMatches({{100, 0x11111111},
+                           {101, 0x22222222},
+                           {102, 0x33333333},
+                           {103, 0x44444444},
+                           {200, 0x22222222}}));
+}
+
+TEST_F(TTATest, AluAbsAndNeg) {
+  const std::vector<int32_t> values = {0,          1,         -1,
+                                       666,        -666,      INT32_MAX,
+                                       INT32_MIN,  0x12345678, -0x12345678};
+  Program program;
+  for (size_t i = 0; i < values.size(); i++) {
+    program
+        .Add(Instr()
+                 .Src(Unit::UNIT_ABS_OPERAND)
+                 .Soperand(values[i])
+                 .Dst(Unit::UNIT_REGISTER)
+                 .Di(0))
+        .Alu(0, 0, ALUOp::ALU_NEG, 0)
+        .Mov(Unit::UNIT_ALU_RESULT, 0, Unit::UNIT_REGISTER, 1)
+        .Mov(Unit::UNIT_REGISTER, 1, Unit::UNIT_MEMORY_IMMEDIATE, 100 + i)
+        .Alu(1, 1, ALUOp::ALU_NEG, 1)
+        .Mov(Unit::UNIT_ALU_RESULT, 1, Unit::UNIT_MEMORY_IMMEDIATE, 200 + i)
+        .Alu(0, 0, ALUOp::ALU_ABS, 2)
+        .Mov(Unit::UNIT_ALU_RESULT, 2, Unit::UNIT_MEMORY_IMMEDIATE, 300 + i);
+  }
+  ASSERT_TRUE(RunProgram(program, 5000).has_value());
+
+  for (size_t i = 0; i < values.size(); i++) {
+    uint32_t x = values[i];
+    EXPECT_EQ(ram()->mem()[100 + i], 0U - x) << FormatWord(x);
+    // neg(neg(x)) == x.
+    EXPECT_EQ(ram()->mem()[200 + i], x) << FormatWord(x);
+    if (values[i] == INT32_MIN) {
+      EXPECT_EQ(ram()->mem()[300 + i], x);
+    } else {
+      EXPECT_GE(DataSigned(300 + i), 0) << FormatWord(x);
+      EXPECT_EQ(DataSigned(300 + i), std::abs(values[i])) << FormatWord(x);
+    }
+  }
+}
+
+TEST_F(TTATest, AluIdentityElements) {
+  const std::vector<uint32_t> values = {0, 1, 666, 0x80000000, 0xffffffff};
+  std::vector<ALUOp> ops;
+  for (ALUOp op : kAllALUOps) {
+    if (IdentityElement(op))
+      ops.push_back(op);
+  }
+  ASSERT_EQ(ops.size(), 8);
+
+  Program program;
+  for (size_t o = 0; o < ops.size(); o++) {
+    for (size_t i = 0; i < values.size(); i++) {
+      program
+          .Add(Instr()
+                   .Src(Unit::UNIT_ABS_OPERAND)
+                   .Soperand(values[i])
+                   .Dst(Unit::UNIT_REGISTER)
+                   .Di(0))
+          .Add(Instr()
+                   .Src(Unit::UNIT_ABS_OPERAND)
+                   .Soperand(*IdentityElement(ops[o]))
+                   .Dst(Unit::UNIT_REGISTER)
+                   .Di(1))
+          .Alu(0, 1, ops[o], 0)
+          .Mov(Unit::UNIT_ALU_RESULT, 0, Unit::UNIT_MEMORY_IMMEDIATE,
+               o * values.size() + i);
+    }
+  }
+  ASSERT_TRUE(RunProgram(program, 20000).has_value());
+
+  for (size_t o = 0; o < ops.size(); o++) {
+    for (size_t i = 0; i < values.size(); i++) {
+      EXPECT_EQ(ram()->mem()[o * values.size() + i], values[i])
+          << ops[o] << " " << FormatWord(values[i]);
+    }
+  }
+}
+
+TEST_F(TTATest, AluSignedDivision) {
+  struct Case {
+    int32_t a, b, quotient, remainder;
+  };
+  const std::vector<Case> cases = {
+      {-7, 2, -3, -1},        {7, -2, -3, 1},  {-7, -2, 3, -1},
+      {7, 2, 3, 1},           {5, 0, 0, 0},    {INT32_MIN, -1, INT32_MIN, 0},
+      {INT32_MIN, 2, -0x40000000, 0}};
+  Program program;
+  for (size_t i = 0; i < cases.size(); i++) {
+    program
+        .Add(Instr()
+                 .Src(Unit::UNIT_ABS_OPERAND)
+                 .Soperand(cases[i].a)
+                 .Dst(Unit::UNIT_REGISTER)
+                 .Di(0))
+        .Add(Instr()
+                 .Src(Unit::UNIT_ABS_OPERAND)
+                 .Soperand(cases[i].b)
+                 .Dst(Unit::UNIT_REGISTER)
+                 .Di(1))
+        .Alu(0, 1, ALUOp::ALU_SDIV, 0)
+        .Mov(Unit::UNIT_ALU_RESULT, 0, Unit::UNIT_MEMORY_IMMEDIATE, 100 + i)
+        .Alu(0, 1, ALUOp::ALU_SMOD, 1)
+        .Mov(Unit::UNIT_ALU_RESULT, 1, Unit::UNIT_MEMORY_IMMEDIATE, 200 + i)
+        .Alu(0, 1, ALUOp::ALU_DIV, 2)
+        .Mov(Unit::UNIT_ALU_RESULT, 2, Unit::UNIT_MEMORY_IMMEDIATE, 300 + i);
+  }
+  ASSERT_TRUE(RunProgram(program, 5000).has_value());
+
+  for (size_t i = 0; i < cases.size(); i++) {
+    EXPECT_EQ(DataSigned(100 + i), cases[i].quotient)
+        << cases[i].a << " / " << cases[i].b;
+    EXPECT_EQ(DataSigned(200 + i), cases[i].remainder)
+        << cases[i].a << " % " << cases[i].b;
+  }
+  // Unsigned division sees -7 as 0xfffffff9.
+  EXPECT_EQ(ram()->mem()[300], 0xfffffff9U / 2);
+}
+
+TEST_F(TTATest, AluShiftAmountsWrapAt32) {
+  const uint32_t x = 0x80000001;
+  Program program = Program().LoadConstant(x, 0);
+  for (short n = 0; n <= 64; n++) {
+    program.LoadConstant(n, 1)
+        .Alu(0, 1, ALUOp::ALU_SL, 0)
+        .Mov(Unit::UNIT_ALU_RESULT, 0, Unit::UNIT_MEMORY_IMMEDIATE, 100 + n)
+        .Alu(0, 1, ALUOp::ALU_SR, 1)
+        .Mov(Unit::UNIT_ALU_RESULT, 1, Unit::UNIT_MEMORY_IMMEDIATE, 200 + n);
+  }
+  ASSERT_TRUE(RunProgram(program, 20000).has_value());
+
+  for (uint32_t n = 0; n <= 64; n++) {
+    EXPECT_EQ(ram()->mem()[100 + n], x << (n & 31)) << "<< " << n;
+    EXPECT_EQ(ram()->mem()[200 + n], x >> (n & 31)) << ">> " << n;
+  }
+}
+
+TEST_F(TTATest, AluResultComputedWhenRead) {
+  Load(Program()
+           .Mov(Unit::UNIT_ABS_IMMEDIATE, 666, Unit::UNIT_ALU_LEFT, 0)
+           .Mov(Unit::UNIT_ABS_IMMEDIATE, 111, Unit::UNIT_ALU_RIGHT, 0)
+           .Mov(Unit::UNIT_ABS_IMMEDIATE, (short)ALUOp::ALU_ADD,
+                Unit::UNIT_ALU_OPERATOR, 0)
+           .Mov(Unit::UNIT_ALU_RESULT, 0, Unit::UNIT_MEMORY_IMMEDIATE, 100)
+           .Mov(Unit::UNIT_ABS_IMMEDIATE, 1, Unit::UNIT_ALU_LEFT, 0)
+           .Mov(Unit::UNIT_ALU_RESULT, 0, Unit::UNIT_MEMORY_IMMEDIATE, 101));
+  Step();  // First clock, still in reset
+  RunUntil(60);
+
+  // The stored result stays put; reading again uses the new left input.
+  EXPECT_EQ(ram()->mem()[100], 777);
+  EXPECT_EQ(ram()->mem()[101], 112);
+}
+
+TEST_F(TTATest, AluCountZeros) {
+  // (value, leading zeros, trailing zeros)
+  const std::vector<std::tuple<uint32_t, uint32_t, uint32_t>> cases = {
+      {0, 32, 32},          {1, 31, 0},           {8, 28, 3},
+      {0x80000000, 0, 31},  {0xffffffff, 0, 0},   {0x00f00000, 8, 20},
+  };
+  Program program;
+  for (size_t i = 0; i < cases.size(); i++) {
+    program
+        .Add(Instr()
+                 .Src(Unit::UNIT_ABS_OPERAND)
+                 .Soperand(std::get<0>(cases[i]))
+                 .Dst(Unit::UNIT_REGISTER)
+                 .Di(0))
+        .Alu(0, 0, ALUOp::ALU_CLZ, 0)
+        .Mov(Unit::UNIT_ALU_RESULT, 0, Unit::UNIT_MEMORY_IMMEDIATE, 100 + i)
+        .Alu(0, 0, ALUOp::ALU_CTZ, 1)
+        .Mov(Unit::UNIT_ALU_RESULT, 1, Unit::UNIT_MEMORY_IMMEDIATE, 200 + i);
+  }
+  ASSERT_TRUE(RunProgram(program, 5000).has_value());
+  for (size_t i = 0; i < cases.size(); i++) {
+    const auto& [value, leading, trailing] = cases[i];
+    EXPECT_EQ(ram()->mem()[100 + i], leading) << FormatWord(value);
+    EXPECT_EQ(ram()->mem()[200 + i], trailing) << FormatWord(value);
+  }
+}
+
+TEST_F(TTATest, AluPopulationCount) {
+  std::mt19937 rng(2103);
+  std::vector<uint32_t> values = {0, 1, 0xffffffff, 0x80000001};
+  for (int i = 0; i < 8; i++)
+    values.push_back(rng());
+
+  // Count each value and its complement.
+  Program program;
+  for (size_t i = 0; i < values.size(); i++) {
+    program
+        .Add(Instr()
+                 .Src(Unit::UNIT_ABS_OPERAND)
+                 .Soperand(values[i])
+                 .Dst(Unit::UNIT_REGISTER)
+                 .Di(0))
+        .Add(Instr()
+                 .Src(Unit::UNIT_ABS_OPERAND)
+                 .Soperand(~values[i])
+                 .Dst(Unit::UNIT_REGISTER)
+                 .Di(1))
+        .Alu(0, 0, ALUOp::ALU_POPCNT, 0)
+        .Mov(Unit::UNIT_ALU_RESULT, 0, Unit::UNIT_MEMORY_IMMEDIATE, 100 + i)
+        .Alu(1, 1, ALUOp::ALU_POPCNT, 1)
+        .Mov(Unit::UNIT_ALU_RESULT, 1, Unit::UNIT_MEMORY_IMMEDIATE, 200 + i);
+  }
+  ASSERT_TRUE(RunProgram(program, 10000).has_value());
+  for (size_t i = 0; i < values.size(); i++) {
+    EXPECT_EQ(ram()->mem()[100 + i], std::bitset<32>(values[i]).count())
+        << FormatWord(values[i]);
+    EXPECT_EQ(ram()->mem()[100 + i] + ram()->mem()[200 + i], 32)
+        << FormatWord(values[i]);
+  }
+}
+
+// A random straight-line program over registers 0-3, ALUs 0-1 and data
+// memory 100-107. It starts by setting every ALU input, since reset
+// doesn't clear them.
+Program RandomProgram(std::mt19937& rng, int moves) {
+  Program program;
+  for (short alu = 0; alu < 2; alu++) {
+    program.Mov(Unit::UNIT_ABS_IMMEDIATE, 0, Unit::UNIT_ALU_LEFT, alu)
+        .Mov(Unit::UNIT_ABS_IMMEDIATE, 0, Unit::UNIT_ALU_RIGHT, alu);
+  }
+  const Unit sources[] = {Unit::UNIT_ABS_IMMEDIATE, Unit::UNIT_REGISTER,
+                          Unit::UNIT_MEMORY_IMMEDIATE, Unit::UNIT_ALU_LEFT,
+                          Unit::UNIT_ALU_RESULT};
+  const Unit dests[] = {Unit::UNIT_REGISTER, Unit::UNIT_ALU_LEFT,
+                        Unit::UNIT_ALU_RIGHT, Unit::UNIT_ALU_OPERATOR,
+                        Unit::UNIT_MEMORY_IMMEDIATE};
+  auto index = [&rng](Unit u) -> short {
+    switch (u) {
+      case Unit::UNIT_ABS_IMMEDIATE:
+        return rng() % 4096;
+      case Unit::UNIT_REGISTER:
+        return rng() % 4;
+      case Unit::UNIT_MEMORY_IMMEDIATE:
+        return 100 + rng() % 8;
+      default:
+        return rng() % 2;
+    }
+  };
+  for (int i = 0; i < moves; i++) {
+    Unit src = sources[rng() % std::size(sources)];
+    Unit dst = dests[rng() % std::size(dests)];
+    if (dst == Unit::UNIT_ALU_OPERATOR) {
+      ALUOp op = kAllALUOps[rng() % std::size(kAllALUOps)];
+      program.Mov(Unit::UNIT_ABS_IMMEDIATE, (short)op, dst, index(dst));
+    } else {
+      program.Mov(src, index(src), dst, index(dst));
+    }
+  }
+  return program;
+}
+
+// With no interpreter to compare against, the optimizer serves as the
+// second implementation: random programs must run to completion on the
+// core and leave the same data memory whether optimized or not.
+TEST_F(TTATest, RandomProgramsMatchWhenOptimized) {
+  std::mt19937 rng(2105);
+  for (int i = 0; i < 20; i++) {
+    Program program = RandomProgram(rng, 40);
+    Program optimized = Optimize(program);
+    int budget = 4 * program.TotalCycles();
+
+    ASSERT_TRUE(RunProgram(program, budget).has_value()) << "program " << i;
+    std::vector<std::pair<uint32_t, uint32_t>> expected =
+        DumpDataRange(100, 108);
+    ASSERT_TRUE(RunProgram(optimized, budget).has_value()) << "program " << i;
+    EXPECT_EQ(DumpDataRange(100, 108), expected)
+        << "program " << i << ":\n" << ToCppSource(program);
+  }
+}
+
+TEST(AssemblerTest, ProgramsRoundtripThroughDecode) {
+  std::vector<Program> corpus = {
+      Program()
+          .LoadConstant(0x80000000, 0)
+          .Alu(0, 1, ALUOp::ALU_SDIV, 2)
+          .Mov(Unit::UNIT_ALU_RESULT, 2, Unit::UNIT_MEMORY_IMMEDIATE, 123),
+      Program()
+          .Add(Instr()
+                   .Src(Unit::UNIT_MEMORY_OPERAND)
+                   .Soperand(0x1234)
+                   .Dst(Unit::UNIT_MEMORY_OPERAND)
+                   .Doperand(0))
+          .Add(Instr::ReadPc(Unit::UNIT_REGISTER, 3))
+          .Add(Instr::Nop())
+          .Mov(Unit::UNIT_ALU_RESULT, 1, Unit::UNIT_PC_IF, 1)};
+  std::mt19937 rng(2120);
+  for (int i = 0; i < 20; i++)
+    corpus.push_back(RandomProgram(rng, 40));
+  for (size_t i = 0; i < corpus.size(); i++) {
+    RoundtripError error;
+    EXPECT_TRUE(VerifyRoundtrip(corpus[i].assemble(), &error))
+        << "program " << i << ": " << error;
+  }
+
+  // Words the decoder takes but the assembler won't produce.
+  RoundtripError error;
+  std::vector<uint32_t> words =
+      Program().Mov(Unit::UNIT_REGISTER, 0, Unit::UNIT_REGISTER, 1).assemble();
+  words.push_back((uint32_t)Unit::UNIT_ABS_IMMEDIATE << kDstUnitShift);
+  ASSERT_FALSE(VerifyRoundtrip(words, &error));
+  EXPECT_EQ(error.pos, 1);
+  ASSERT_TRUE(error.assemble_error.has_value());
+  std::ostringstream os;
+  os << error;
+  EXPECT_EQ(os.str(),
+            "0001: UNIT_ABS_IMMEDIATE can't be used as a destination");
+
+  words = Instr()
+              .Src(Unit::UNIT_ABS_OPERAND)
+              .Soperand(1)
+              .Dst(Unit::UNIT_REGISTER)
+              .assemble();
+  words.pop_back();
+  ASSERT_FALSE(VerifyRoundtrip(words, &error));
+  EXPECT_TRUE(error.decode_error.has_value());
+}
+
+TEST(AssemblerTest, ToCppSource) {
+  Program program = {
+      Instr()
+          .Src(Unit::UNIT_ABS_OPERAND)
+          .Soperand(0x1234)
+          .Dst(Unit::UNIT_MEMORY_OPERAND)
+          .Doperand(0x100),
+      Instr().Src(Unit::UNIT_REGISTER).Si(2).Dst(Unit::UNIT_ALU_LEFT),
+      Instr::Nop().Comment("dropped")};
+  EXPECT_EQ(ToCppSource(program),
+            "Instr().Src(Unit::UNIT_ABS_OPERAND).Soperand(0x1234)"
+            ".Dst(Unit::UNIT_MEMORY_OPERAND).Doperand(0x100),\n"
+            "Instr().Src(Unit::UNIT_REGISTER).Si(2).Dst(Unit::UNIT_ALU_LEFT),\n"
+            "Instr().Src(Unit::UNIT_NONE).Dst(Unit::UNIT_NONE)");
+
+  // What it prints builds the same program back.
+  Program rebuilt = {
+      Instr()
+          .Src(Unit::UNIT_ABS_OPERAND)
+          .Soperand(0x1234)
+          .Dst(Unit::UNIT_MEMORY_OPERAND)
+          .Doperand(0x100),
+      Instr().Src(Unit::UNIT_REGISTER).Si(2).Dst(Unit::UNIT_ALU_LEFT),
+      Instr().Src(Unit::UNIT_NONE).Dst(Unit::UNIT_NONE)};
+  EXPECT_TRUE(DiffPrograms(program, rebuilt).empty());
+}
+
+TEST_F(TTATest, MoveTraceShowsTransportedValues) {
+  EnableMoveTrace();
+  Program program = {Instr()
+                         .Src(Unit::UNIT_ABS_IMMEDIATE)
+                         .Si(666)
+                         .Dst(Unit::UNIT_ALU_LEFT)
+                         .Di(0),
+                     Instr()
+                         .Src(Unit::UNIT_ABS_IMMEDIATE)
+                         .Si(111)
+                         .Dst(Unit::UNIT_ALU_RIGHT)
+                         .Di(0),
+                     Instr()
+                         .Src(Unit::UNIT_ABS_IMMEDIATE)
+                         .Si((int)ALUOp::ALU_ADD)
+                         .Dst(Unit::UNIT_ALU_OPERATOR)
+                         .Di(0),
+                     Instr()
+                         .Src(Unit::UNIT_ALU_RESULT)
+                         .Si(0)
+                         .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+                         .Di(123)};
+  Load(program);
+  Step();  // First clock, still in reset
+  RunUntil(17);
+
+  ASSERT_GE(move_trace().size(), 4);
+  std::vector<uint32_t> expected = {666, 111, (uint32_t)ALUOp::ALU_ADD, 777};
+  for (size_t i = 0; i < expected.size(); i++) {
+    EXPECT_EQ(move_trace()[i].instr, *std::next(program.begin(), i));
+    EXPECT_EQ(move_trace()[i].value, expected[i])
+        << move_trace()[i].instr << ": " << FormatWord(move_trace()[i].value);
+  }
+}
+
+TEST_F(TTATest, ClearDataAndInstructionsSeparately) {
+  Load({Instr()
+            .Src(Unit::UNIT_ABS_IMMEDIATE)
+            .Si(666)
+            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Di(123)});
+  Step();  // First clock, still in reset
+  RunUntil(8);
+  ASSERT_EQ(ram()->mem()[123], 666);
+
+  ClearData();
+  EXPECT_EQ(ram()->mem()[123], 0);
+  EXPECT_NE(prg()->mem()[0], 0);
+
+  ClearInstructions();
+  EXPECT_TRUE(std::all_of(prg()->mem().begin(), prg()->mem().end(),
+                          [](IData word) { return word == 0; }));
+}
+
+TEST_F(TTATest, UninitializedReadsSeeTheFill) {
+  SetUninitializedFill(0xdeadbeef);
+  Load({Instr()
+            .Src(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Si(50)
+            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Di(51),
+        Instr()
+            .Src(Unit::UNIT_ABS_IMMEDIATE)
+            .Si(7)
+            .Dst(Unit::UNIT_MEMORY_IMMEDIATE)
+            .Di(52)});
+  Step();  // First clock, still in reset
+  RunUntil(16);
+
+  EXPECT_EQ(ram()->mem()[51], 0xdeadbeef) << FormatWord(ram()->mem()[51]);
+  EXPECT_EQ(DumpState().data_mem, (std::map<uint32_t, uint32_t>{{52, 7}}));
+
+  ResetModel();
+  EXPECT_EQ(ram()->mem()[51], 0xdeadbeef);
+}
+
+TEST_F(TTATest, LoadConstantIntoRegister) {
+  for (uint32_t value : {0U, 0xfffU, 0x1000U, 0x80000000U, 0xffffffffU}) {
+    Program program = Program().LoadConstant(value, 3).Mov(
+        Unit::UNIT_REGISTER, 3, Unit::UNIT_MEMORY_IMMEDIATE, 10);
+    EXPECT_EQ(program.LenWords(), value <= kSiMask ? 2 : 3);
+    ASSERT_TRUE(RunProgram(program, 50).has_value()) << FormatWord(value);
+    EXPECT_EQ(ram()->mem()[10], value) << FormatWord(ram()->mem()[10]);
+  }
+}
+
+TEST_F(TTATest, LastDoneCycle) {
+  Load(Program()
+           .Mov(Unit::UNIT_ABS_IMMEDIATE, 666, Unit::UNIT_REGISTER, 0)
+           .Mov(Unit::UNIT_REGISTER, 0, Unit::UNIT_MEMORY_IMMEDIATE, 123));
+  EXPECT_FALSE(last_done_cycle().has_value());
+  Step();  // First clock, still in reset
+
+  std::vector<int> done_cycles;
+  for (int retired = 1; retired <= 2; retired++) {
+    while (instructions_retired() < retired && clk().cycles() < 50)
+      Step();
+    ASSERT_EQ(instructions_retired(), retired);
+    EXPECT_EQ(last_done_cycle(), clk().cycles());
+    done_cycles.push_back(clk().cycles());
+  }
+  EXPECT_LT(done_cycles[0], done_cycles[1]);
+
+  RunUntil(5);
+  EXPECT_GE(*last_done_cycle(), done_cycles[1]);
+
+  ResetModel();
+  EXPECT_FALSE(last_done_cycle().has_value());
+}
+
+TEST_F(TTATest, DisassembleLoaded) {
+  EXPECT_EQ(DisassembleLoaded(), "");
+  Load(Program()
+           .Mov(Unit::UNIT_ABS_IMMEDIATE, 666, Unit::UNIT_REGISTER, 0)
+           .Add(Instr()
+                    .Src(Unit::UNIT_REGISTER)
+                    .Si(0)
+                    .Dst(Unit::UNIT_MEMORY_OPERAND)
+                    .Doperand(300))
+           .Mov(Unit::UNIT_REGISTER, 0, Unit::UNIT_MEMORY_IMMEDIATE, 123));
+  EXPECT_EQ(DisassembleLoaded(),
+            "0000: R00 := #29a\n"
+            "0001: *(0000012c) := R00\n"
+            "0003: *(07b) := R00\n");
+}
@@ -0,0 +1,148 @@
+#include <absl/flags/flag.h>
+#include <absl/flags/parse.h>
+#include <glog/logging.h>
+
+#include <fstream>
+#include <iomanip>
+#include <iostream>
+#include <sstream>
+#include <string>
+#include <vector>
+
+#include "assembler.h"
+#include "tta_harness.h"
+#include "validator.h"
+
+// Command line tools for working with TTA programs:
+//
+//   tta_tool disasm program.bin
+//       Print one move per line, with its word offset.
+//
+//   tta_tool asm input.tta output.bin
+//       Assemble text in the notation disasm prints into a binary,
+//       printing ValidateProgram() warnings.
+//
+//   tta_tool run program.bin --cycles=500 --dump=100..110
+//       Load a program at address 0, run it for the given number of
+//       cycles and print the requested (half-open) range of data memory.
+
+ABSL_FLAG(int, cycles, 500, "Number of cycles to run for");
+ABSL_FLAG(std::string,
+          dump,
+          "",
+          "Range of data memory to print after running, as start..end");
+ABSL_FLAG(std::string, trace_file, "", "Trace file");
+
+namespace {
+
+int Disassemble(const std::string& path) {
+  std::optional<std::vector<uint32_t>> words = ReadProgramBinary(path);
+  if (!words) {
+    std::cerr << "Can't read program: " << path << std::endl;
+    return EXIT_FAILURE;
+  }
+  size_t pos = 0;
+  while (pos < words->size()) {
+    Instr instr;
+    size_t len;
+    DecodeError error;
+    if (!Instr::TryDecode(*words, pos, &instr, &len, &error)) {
+      std::cerr << std::hex << std::setw(4) << std::setfill('0') << pos
+                << std::dec << ": " << error << std::endl;
+      return EXIT_FAILURE;
+    }
+    std::cout << std::hex << std::setw(4) << std::setfill('0') << pos
+              << std::dec << ": " << instr << std::endl;
+    pos += len;
+  }
+  return EXIT_SUCCESS;
+}
+
+int Assemble(const std::string& input, const std::string& output) {
+  std::ifstream in(input);
+  if (!in) {
+    std::cerr << "Can't read source: " << input << std::endl;
+    return EXIT_FAILURE;
+  }
+  std::stringstream text;
+  text << in.rdbuf();
+
+  Program program;
+  ParseError error;
+  if (!ParseAsm(text.str(), &program, &error)) {
+    std::cerr << input << ":" << error.line << ": " << error.message
+              << std::endl
+              << "  " << error.text << std::endl;
+    return EXIT_FAILURE;
+  }
+  for (const ProgramWarning& warning : ValidateProgram(program))
+    std::cerr << input << ": warning: " << warning << std::endl;
+  if (!WriteProgramBinary(output, program.assemble())) {
+    std::cerr << "Can't write program: " << output << std::endl;
+    return EXIT_FAILURE;
+  }
+  return EXIT_SUCCESS;
+}
+
+int Run(const std::string& path) {
+  std::optional<std::vector<uint32_t>> words = ReadProgramBinary(path);
+  if (!words) {
+    std::cerr << "Can't read program: " << path << std::endl;
+    return EXIT_FAILURE;
+  }
+
+  uint32_t dump_start = 0, dump_end = 0;
+  std::string dump = absl::GetFlag(FLAGS_dump);
+  if (!dump.empty()) {
+    char dots[3] = {};
+    std::istringstream range(dump);
+    if (!(range >> dump_start >> dots[0] >> dots[1] >> dump_end) ||
+        std::string(dots) != ".." || dump_start > dump_end) {
+      std::cerr << "Bad --dump range: " << dump << std::endl;
+      return EXIT_FAILURE;
+    }
+  }
+
+  TTAHarness harness(absl::GetFlag(FLAGS_trace_file));
+  if (words->size() > harness.prg()->mem().size() ||
+      dump_end > harness.ram()->mem().size()) {
+    std::cerr << "Program or --dump range doesn't fit in memory" << std::endl;
+    return EXIT_FAILURE;
+  }
+  harness.Reset();
+  harness.Load(*words);
+  harness.RunUntil(absl::GetFlag(FLAGS_cycles));
+
+  for (uint32_t addr = dump_start; addr < dump_end; addr++) {
+    std::cout << std::hex << std::setw(4) << std::setfill('0') << addr
+              << std::dec << ": " << FormatWord(harness.ram()->mem()[addr])
+              << std::endl;
+  }
+  return EXIT_SUCCESS;
+}
+
+void Usage() {
+  std::cerr << "usage: tta_tool disasm <program.bin>" << std::endl
+            << "       tta_tool asm <input.tta> <output.bin>" << std::endl
+            << "       tta_tool run <program.bin> [--cycles=N] "
+               "[--dump=start..end] [--trace_file=file]"
+            << std::endl;
+}
+
+}  // namespace
+
+int main(int argc, char** argv) {
+  FLAGS_logtostderr = true;
+  google::InitGoogleLogging(argv[0]);
+  std::vector<char*> args = absl::ParseCommandLine(argc, argv);
+
+  if (args.size() == 3 && std::string(args[1]) == "disasm")
+    return Disassemble(args[2]);
+  if (args.size() == 4 && std::string(args[1]) == "asm")
+    return Assemble(args[2], args[3]);
+  if (args.size() == 3 && std::string(args[1]) == "run")
+    return Run(args[2]);
+
+  Usage();
+  return EXIT_FAILURE;
+}
@@ -0,0 +1,99 @@
+#include "validator.h"
+
+#include <algorithm>
+#include <iterator>
+#include <optional>
+
+namespace {
+
+// How many of the thing a unit's immediate selects the core has, if it
+// selects one at all.
+std::optional<int> IndexLimit(Unit u) {
+  switch (u) {
+    case Unit::UNIT_REGISTER:
+    case Unit::UNIT_REGISTER_POINTER:
+      return kNumRegisters;
+    case Unit::UNIT_ALU_LEFT:
+    case Unit::UNIT_ALU_RIGHT:
+    case Unit::UNIT_ALU_OPERATOR:
+    case Unit::UNIT_ALU_RESULT:
+    case Unit::UNIT_PC_IF:
+      return kNumAlus;
+    default:
+      return std::nullopt;
+  }
+}
+
+bool IsImplemented(Unit u) {
+  switch (u) {
+    case Unit::UNIT_STACK_PUSH_POP:
+    case Unit::UNIT_STACK_INDEX:
+      return false;
+    default:
+      return (int)u <= (int)Unit::UNIT_PC_IF;
+  }
+}
+
+bool IsALUOp(uint32_t value) {
+  return std::any_of(std::begin(kAllALUOps), std::end(kAllALUOps),
+                     [value](ALUOp op) { return (uint32_t)op == value; });
+}
+
+}  // namespace
+
+std::ostream& operator<<(std::ostream& os, const ProgramWarning& w) {
+  os << "move " << w.index << ": ";
+  const char* role = w.role == Role::SOURCE ? "source" : "destination";
+  switch (w.kind) {
+    case ProgramWarning::Kind::UNKNOWN_ALU_OP:
+      return os << "value moved to " << w.unit << " isn't an ALU operator";
+    case ProgramWarning::Kind::INDEX_OUT_OF_RANGE:
+      return os << role << " " << w.unit << " index is out of range";
+    case ProgramWarning::Kind::UNIMPLEMENTED_UNIT:
+      return os << role << " " << w.unit << " isn't implemented";
+    case ProgramWarning::Kind::READS_NOTHING:
+      return os << "moves 0 from UNIT_NONE into " << w.unit;
+  }
+  return os;
+}
+
+std::vector<ProgramWarning> ValidateProgram(const Program& program) {
+  std::vector<ProgramWarning> warnings;
+  size_t index = 0;
+  for (const Instr& instr : program) {
+    auto check = [&](Unit unit, unsigned short immediate, Role role) {
+      if (!IsImplemented(unit)) {
+        warnings.push_back(
+            {ProgramWarning::Kind::UNIMPLEMENTED_UNIT, index, unit, role});
+        return;
+      }
+      std::optional<int> limit = IndexLimit(unit);
+      if (limit && immediate >= *limit) {
+        warnings.push_back(
+            {ProgramWarning::Kind::INDEX_OUT_OF_RANGE, index, unit, role});
+      }
+    };
+    check(instr.src_unit(), instr.si(), Role::SOURCE);
+    check(instr.dst_unit(), instr.di(), Role::DESTINATION);
+
+    if (instr.src_unit() == Unit::UNIT_NONE &&
+        instr.dst_unit() != Unit::UNIT_NONE) {
+      warnings.push_back({ProgramWarning::Kind::READS_NOTHING, index,
+                          instr.dst_unit(), Role::DESTINATION});
+    }
+
+    if (instr.dst_unit() == Unit::UNIT_ALU_OPERATOR) {
+      std::optional<uint32_t> value;
+      if (instr.src_unit() == Unit::UNIT_ABS_IMMEDIATE)
+        value = instr.si();
+      else if (instr.src_unit() == Unit::UNIT_ABS_OPERAND)
+        value = instr.soperand();
+      if (value && !IsALUOp(*value)) {
+        warnings.push_back({ProgramWarning::Kind::UNKNOWN_ALU_OP, index,
+                            Unit::UNIT_ALU_OPERATOR, Role::DESTINATION});
+      }
+    }
+    index++;
+  }
+  return warnings;
+}
@@ -0,0 +1,35 @@
+#pragma once
+
+#include <cstddef>
+#include <ostream>
+#include <vector>
+
+#include "assembler.h"
+
+// Something in a program that assembles fine but won't do what it looks
+// like on the core.
+struct ProgramWarning {
+  enum class Kind {
+    // An immediate or operand moved into UNIT_ALU_OPERATOR isn't an ALUOp.
+    UNKNOWN_ALU_OP,
+    // A register or ALU index past the last one the core has. The RTL
+    // doesn't check these.
+    INDEX_OUT_OF_RANGE,
+    // The stack units, and unit codes with no Unit, decode but do nothing.
+    UNIMPLEMENTED_UNIT,
+    // A move from UNIT_NONE into a real destination, which stores 0 there;
+    // usually a forgotten Src(). Reported against the destination.
+    READS_NOTHING,
+  };
+  Kind kind;
+  // Position of the offending move in the program.
+  size_t index;
+  Unit unit;
+  Role role;
+};
+std::ostream& operator<<(std::ostream& os, const ProgramWarning& w);
+
+// Static checks over `program`, in instruction order. Immediates too wide
+// for their field never get this far: Si() and Di() CHECK-fail on them,
+// and TrySi(), TryDi() and ParseAsm() refuse them.
+std::vector<ProgramWarning> ValidateProgram(const Program& program);